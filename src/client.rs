@@ -0,0 +1,461 @@
+//! A small MCP client for poking at a running server from the command line.
+//!
+//! Connects over stdio (spawning the server) or SSE (to a URL), performs the
+//! `initialize` handshake, issues one request and prints the result either
+//! as JSON or in a form meant for reading.
+
+use std::process::ExitCode;
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, bail};
+use clap::Subcommand;
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
+use rmcp::model::*;
+use rmcp::service::RunningService;
+use rmcp::transport::{SseTransport, TokioChildProcess};
+use rmcp::{RoleClient, ServiceExt};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Where the server is and how to reach it.
+#[derive(clap::Args, Debug, Clone)]
+pub struct ConnectArgs {
+    /// URL of the server's SSE endpoint, e.g. http://127.0.0.1:8000/sse
+    #[arg(long, conflicts_with = "stdio", required_unless_present = "stdio")]
+    pub sse: Option<String>,
+
+    /// Command that starts a server speaking MCP on stdin/stdout, split like a shell would,
+    /// e.g. "xp_both_mcp -t stdio --log-target none"
+    #[arg(long)]
+    pub stdio: Option<String>,
+
+    /// Bearer token sent with SSE requests
+    #[arg(long, env = "XP_MCP_TOKEN", hide_env_values = true)]
+    pub token: Option<String>,
+
+    /// Seconds to wait for the connection and each request
+    #[arg(long, default_value_t = 30)]
+    pub timeout: u64,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ClientArgs {
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// Print the raw JSON result instead of a readable summary
+    #[arg(long, global = true)]
+    pub json: bool,
+
+    #[command(subcommand)]
+    pub request: Request,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Request {
+    /// List the tools the server offers
+    ListTools,
+    /// Call a tool
+    Call {
+        /// Tool name
+        tool: String,
+        /// Argument as key=value; values are JSON unless the tool's schema says string
+        #[arg(short, long = "arg", value_parser = parse_key_value)]
+        args: Vec<(String, String)>,
+    },
+    /// List the resources the server offers
+    ListResources,
+    /// Read a resource
+    Read {
+        /// Resource URI, e.g. counter://default
+        uri: String,
+    },
+    /// List the prompts the server offers
+    ListPrompts,
+    /// Render a prompt
+    GetPrompt {
+        /// Prompt name
+        name: String,
+        /// Argument as key=value
+        #[arg(short, long = "arg", value_parser = parse_key_value)]
+        args: Vec<(String, String)>,
+    },
+}
+
+pub type Connection = RunningService<RoleClient, ClientInfo>;
+
+/// Run the `client` subcommand; fails with a non-zero exit if a tool reports an error.
+pub async fn run(args: ClientArgs) -> Result<ExitCode> {
+    let timeout = Duration::from_secs(args.connect.timeout);
+    let connection = connect(&args.connect).await?;
+    let result = tokio::time::timeout(timeout, send(&connection, args.request, args.json))
+        .await
+        .map_err(|_| anyhow!("no response within {}s", timeout.as_secs()));
+    connection.cancel().await?;
+    result?
+}
+
+/// Connect and complete the `initialize` handshake.
+pub async fn connect(args: &ConnectArgs) -> Result<Connection> {
+    let timeout = Duration::from_secs(args.timeout);
+    let connecting = async {
+        match (&args.sse, &args.stdio) {
+            (Some(url), _) => {
+                let mut headers = HeaderMap::new();
+                if let Some(token) = &args.token {
+                    let value = HeaderValue::from_str(&format!("Bearer {token}"))
+                        .context("token contains characters not allowed in a header")?;
+                    headers.insert(AUTHORIZATION, value);
+                }
+                let http = reqwest::Client::builder()
+                    .default_headers(headers)
+                    .build()?;
+                let transport = SseTransport::start_with_client(url.as_str(), http)
+                    .await
+                    .with_context(|| format!("failed to connect to {url}"))?;
+                Ok(client_info().serve(transport).await?)
+            }
+            (None, Some(command)) => {
+                let words =
+                    shlex::split(command).context("--stdio command has unbalanced quotes")?;
+                let (program, args) = words.split_first().context("--stdio command is empty")?;
+                let mut child = tokio::process::Command::new(program);
+                child.args(args);
+                let transport = TokioChildProcess::new(&mut child)
+                    .with_context(|| format!("failed to start {program}"))?;
+                Ok(client_info().serve(transport).await?)
+            }
+            (None, None) => bail!("set --sse or --stdio"),
+        }
+    };
+    tokio::time::timeout(timeout, connecting)
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "server did not finish initializing within {}s",
+                args.timeout
+            )
+        })?
+}
+
+fn client_info() -> ClientInfo {
+    ClientInfo {
+        protocol_version: Default::default(),
+        capabilities: ClientCapabilities::default(),
+        client_info: Implementation {
+            name: concat!(env!("CARGO_PKG_NAME"), "-client").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+    }
+}
+
+async fn send(connection: &Connection, request: Request, json: bool) -> Result<ExitCode> {
+    let peer = connection.peer();
+    match request {
+        Request::ListTools => {
+            let tools = peer.list_all_tools().await?;
+            output(json, &tools, print_tools)?;
+        }
+        Request::Call { tool, args } => {
+            let schema = if args.is_empty() {
+                None
+            } else {
+                peer.list_all_tools()
+                    .await?
+                    .into_iter()
+                    .find(|t| t.name == tool)
+                    .map(|t| t.input_schema)
+            };
+            let arguments = tool_arguments(schema.as_deref(), args);
+            let result = peer
+                .call_tool(CallToolRequestParam {
+                    name: tool.into(),
+                    arguments: Some(arguments),
+                })
+                .await?;
+            output(json, &result, print_call_result)?;
+            if result.is_error == Some(true) {
+                return Ok(ExitCode::FAILURE);
+            }
+        }
+        Request::ListResources => {
+            let resources = peer.list_all_resources().await?;
+            output(json, &resources, print_resources)?;
+        }
+        Request::Read { uri } => {
+            let result = peer.read_resource(ReadResourceRequestParam { uri }).await?;
+            output(json, &result, print_resource_contents)?;
+        }
+        Request::ListPrompts => {
+            let prompts = peer.list_all_prompts().await?;
+            output(json, &prompts, print_prompts)?;
+        }
+        Request::GetPrompt { name, args } => {
+            let result = peer
+                .get_prompt(GetPromptRequestParam {
+                    name,
+                    arguments: Some(prompt_arguments(args)),
+                })
+                .await?;
+            output(json, &result, print_prompt)?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print `value` as JSON, or with `human` for a readable summary.
+pub fn output<T: Serialize>(json: bool, value: &T, human: fn(&T)) -> Result<()> {
+    if json {
+        println!("{}", serde_json::to_string_pretty(value)?);
+    } else {
+        human(value);
+    }
+    Ok(())
+}
+
+/// Build tool arguments, keeping values as strings where the tool's schema
+/// expects a string so that `--arg name=42` names a counter "42".
+pub fn tool_arguments(schema: Option<&JsonObject>, args: Vec<(String, String)>) -> JsonObject {
+    args.into_iter()
+        .map(|(key, raw)| {
+            let value = match schema {
+                Some(schema) if property_is_string(schema, &key) => Value::String(raw),
+                _ => serde_json::from_str(&raw).unwrap_or(Value::String(raw)),
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// Prompt arguments are always strings.
+pub fn prompt_arguments(args: Vec<(String, String)>) -> JsonObject {
+    args.into_iter()
+        .map(|(key, value)| (key, Value::String(value)))
+        .collect()
+}
+
+fn property_is_string(schema: &JsonObject, key: &str) -> bool {
+    let Some(property) = schema.get("properties").and_then(|p| p.get(key)) else {
+        return false;
+    };
+    match property.get("type") {
+        Some(Value::String(t)) => t == "string",
+        Some(Value::Array(types)) => types.iter().any(|t| t == "string"),
+        _ => false,
+    }
+}
+
+pub fn parse_key_value(s: &str) -> Result<(String, String), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got {s:?}")),
+    }
+}
+
+pub fn print_tools(tools: &Vec<Tool>) {
+    for tool in tools {
+        entry(tool.name.to_string(), Some(&tool.description));
+        let properties = tool
+            .input_schema
+            .get("properties")
+            .and_then(Value::as_object);
+        let required: Vec<&str> = tool
+            .input_schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|r| r.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        for (name, property) in properties.into_iter().flatten() {
+            let kind = match property.get("type") {
+                Some(Value::String(t)) => t.clone(),
+                Some(Value::Array(types)) => types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .filter(|t| *t != "null")
+                    .collect::<Vec<_>>()
+                    .join("|"),
+                _ => "any".to_string(),
+            };
+            let required = if required.contains(&name.as_str()) {
+                ", required"
+            } else {
+                ""
+            };
+            entry(
+                format!("    {name} ({kind}{required})"),
+                property.get("description").and_then(Value::as_str),
+            );
+        }
+    }
+}
+
+/// Text that holds a JSON object or array, indented; anything else as is.
+fn pretty(text: &str) -> String {
+    match serde_json::from_str::<Value>(text) {
+        Ok(value @ (Value::Object(_) | Value::Array(_))) => {
+            serde_json::to_string_pretty(&value).unwrap_or_else(|_| text.to_string())
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// One line of a listing: a name, then its description if it has one.
+fn entry(head: String, description: Option<&str>) {
+    match description.filter(|d| !d.is_empty()) {
+        Some(description) => println!("{head}  {description}"),
+        None => println!("{head}"),
+    }
+}
+
+pub fn print_call_result(result: &CallToolResult) {
+    for content in &result.content {
+        match &content.raw {
+            RawContent::Text(text) => println!("{}", pretty(&text.text)),
+            RawContent::Image(image) => println!(
+                "<image {}, {} base64 bytes>",
+                image.mime_type,
+                image.data.len()
+            ),
+            RawContent::Resource(resource) => print_contents(&resource.resource),
+        }
+    }
+    if result.is_error == Some(true) {
+        eprintln!("tool reported an error");
+    }
+}
+
+pub fn print_resources(resources: &Vec<Resource>) {
+    for resource in resources {
+        let mime = resource.mime_type.as_deref().unwrap_or("-");
+        println!("{}  {}  {}", resource.uri, resource.name, mime);
+        if let Some(description) = &resource.description {
+            println!("    {description}");
+        }
+    }
+}
+
+pub fn print_resource_contents(result: &ReadResourceResult) {
+    for contents in &result.contents {
+        print_contents(contents);
+    }
+}
+
+fn print_contents(contents: &ResourceContents) {
+    match contents {
+        ResourceContents::TextResourceContents { text, .. } => println!("{text}"),
+        ResourceContents::BlobResourceContents {
+            uri,
+            mime_type,
+            blob,
+        } => println!(
+            "<{uri}: {}, {} base64 bytes>",
+            mime_type.as_deref().unwrap_or("binary"),
+            blob.len()
+        ),
+    }
+}
+
+pub fn print_prompts(prompts: &Vec<Prompt>) {
+    for prompt in prompts {
+        entry(prompt.name.clone(), prompt.description.as_deref());
+        for argument in prompt.arguments.iter().flatten() {
+            let required = if argument.required == Some(true) {
+                " (required)"
+            } else {
+                ""
+            };
+            entry(
+                format!("    {}{required}", argument.name),
+                argument.description.as_deref(),
+            );
+        }
+    }
+}
+
+pub fn print_prompt(result: &GetPromptResult) {
+    if let Some(description) = &result.description {
+        println!("# {description}");
+    }
+    for message in &result.messages {
+        let role = match message.role {
+            PromptMessageRole::User => "user",
+            PromptMessageRole::Assistant => "assistant",
+        };
+        match &message.content {
+            PromptMessageContent::Text { text } => println!("{role}: {text}"),
+            PromptMessageContent::Image { image } => {
+                println!("{role}: <image {}>", image.mime_type)
+            }
+            PromptMessageContent::Resource { resource } => {
+                print!("{role}: ");
+                print_contents(&resource.resource);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn schema(value: Value) -> JsonObject {
+        value.as_object().unwrap().clone()
+    }
+
+    fn args(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn string_properties_stay_strings() {
+        let schema = schema(json!({
+            "properties": {
+                "name": { "type": "string" },
+                "delta": { "type": "integer" },
+            }
+        }));
+        let arguments = tool_arguments(
+            Some(&schema),
+            args(&[("name", "42"), ("delta", "42"), ("other", "true")]),
+        );
+        assert_eq!(arguments["name"], json!("42"));
+        assert_eq!(arguments["delta"], json!(42));
+        assert_eq!(arguments["other"], json!(true));
+    }
+
+    #[test]
+    fn arguments_without_a_schema_are_parsed_as_json_when_they_can_be() {
+        let arguments = tool_arguments(None, args(&[("n", "42"), ("s", "not json")]));
+        assert_eq!(arguments["n"], json!(42));
+        assert_eq!(arguments["s"], json!("not json"));
+    }
+
+    #[test]
+    fn a_list_of_types_containing_string_counts_as_string() {
+        let schema = schema(json!({
+            "properties": {
+                "nullable": { "type": ["string", "null"] },
+                "number": { "type": ["integer", "null"] },
+                "untyped": {},
+            }
+        }));
+        assert!(property_is_string(&schema, "nullable"));
+        assert!(!property_is_string(&schema, "number"));
+        assert!(!property_is_string(&schema, "untyped"));
+        assert!(!property_is_string(&schema, "missing"));
+    }
+
+    #[test]
+    fn key_value_pairs() {
+        assert_eq!(parse_key_value("k=v"), Ok(("k".into(), "v".into())));
+        assert_eq!(parse_key_value("k="), Ok(("k".into(), String::new())));
+        assert_eq!(parse_key_value("k=a=b"), Ok(("k".into(), "a=b".into())));
+        assert!(parse_key_value("=v").is_err());
+        assert!(parse_key_value("kv").is_err());
+    }
+}
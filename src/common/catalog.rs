@@ -0,0 +1,451 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use rmcp::model::*;
+use rmcp::{Error as McpError, Peer, RoleServer};
+use serde::{Deserialize, Serialize, Serializer};
+use tokio::sync::broadcast;
+use tokio_util::sync::{CancellationToken, DropGuard};
+
+use super::counter::{Counter, resource_router};
+use super::files::FileRoots;
+use super::notify;
+
+/// A fixed text resource served as configured.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceDef {
+    pub uri: String,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    pub text: String,
+}
+
+/// A prompt whose `template` has `{argument}` placeholders filled in from the request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptDef {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub template: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub arguments: Vec<PromptArgumentDef>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PromptArgumentDef {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// Prompts used when the configuration doesn't list any.
+pub fn default_prompts() -> Vec<PromptDef> {
+    vec![PromptDef {
+        name: "example_prompt".to_string(),
+        description: Some(
+            "This is an example prompt that takes one required argument, message".to_string(),
+        ),
+        template: "This is an example prompt with your message here: '{message}'".to_string(),
+        arguments: vec![PromptArgumentDef {
+            name: "message".to_string(),
+            description: Some("A message to put in the prompt".to_string()),
+            required: true,
+        }],
+    }]
+}
+
+/// The configurable part of what a session offers: static resources, the
+/// directories served as files, prompts, and which of the counter tools may
+/// be called.
+///
+/// Built only from validated definitions; clones share the same data.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Serialize)]
+struct Inner {
+    /// Enabled tools
+    tools: BTreeSet<String>,
+    #[serde(flatten)]
+    files: FileRoots,
+    resources: Vec<ResourceDef>,
+    prompts: Vec<PromptDef>,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self::new(Vec::new(), default_prompts(), None, FileRoots::default())
+            .expect("default catalog is valid")
+    }
+}
+
+impl Serialize for Catalog {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.inner.serialize(serializer)
+    }
+}
+
+impl Catalog {
+    /// Check the definitions against each other; `tools` defaults to every tool.
+    pub fn new(
+        resources: Vec<ResourceDef>,
+        prompts: Vec<PromptDef>,
+        tools: Option<Vec<String>>,
+        files: FileRoots,
+    ) -> Result<Self, String> {
+        let mut uris = HashSet::new();
+        for resource in &resources {
+            if let Some((template, ..)) = resource_router().resolve(&resource.uri) {
+                return Err(format!(
+                    "resource {:?} clashes with the built-in {template} resources",
+                    resource.uri
+                ));
+            }
+            if !uris.insert(&resource.uri) {
+                return Err(format!("resource {:?} is defined twice", resource.uri));
+            }
+        }
+
+        let mut names = HashSet::new();
+        for prompt in &prompts {
+            if !names.insert(&prompt.name) {
+                return Err(format!("prompt {:?} is defined twice", prompt.name));
+            }
+            let arguments: HashSet<&str> =
+                prompt.arguments.iter().map(|a| a.name.as_str()).collect();
+            if arguments.len() != prompt.arguments.len() {
+                return Err(format!("prompt {:?} repeats an argument", prompt.name));
+            }
+            for placeholder in placeholders(&prompt.template)? {
+                if !arguments.contains(placeholder) {
+                    return Err(format!(
+                        "prompt {:?} uses {{{placeholder}}} but declares no such argument",
+                        prompt.name
+                    ));
+                }
+            }
+        }
+
+        let known_tools = Counter::tool_names();
+        let tools = match tools {
+            Some(tools) => {
+                if let Some(unknown) = tools.iter().find(|tool| !known_tools.contains(tool)) {
+                    let mut known_tools = known_tools;
+                    known_tools.sort();
+                    return Err(format!(
+                        "unknown tool {unknown:?}, expected one of {}",
+                        known_tools.join(", ")
+                    ));
+                }
+                tools.into_iter().collect()
+            }
+            None => known_tools.into_iter().collect(),
+        };
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                tools,
+                files,
+                resources,
+                prompts,
+            }),
+        })
+    }
+
+    pub fn tool_enabled(&self, name: &str) -> bool {
+        self.inner.tools.contains(name)
+    }
+
+    pub fn files(&self) -> &FileRoots {
+        &self.inner.files
+    }
+
+    pub fn list_resources(&self) -> Vec<Resource> {
+        self.inner
+            .resources
+            .iter()
+            .map(|resource| {
+                let mut raw = RawResource::new(&resource.uri, resource.name.clone());
+                raw.description = resource.description.clone();
+                raw.mime_type = resource.mime_type.clone();
+                raw.no_annotation()
+            })
+            .collect()
+    }
+
+    pub fn read_resource(&self, uri: &str) -> Option<ResourceContents> {
+        let resource = self.inner.resources.iter().find(|r| r.uri == uri)?;
+        Some(match &resource.mime_type {
+            Some(mime_type) => ResourceContents::TextResourceContents {
+                uri: resource.uri.clone(),
+                mime_type: Some(mime_type.clone()),
+                text: resource.text.clone(),
+            },
+            None => ResourceContents::text(&resource.text, &resource.uri),
+        })
+    }
+
+    pub fn list_prompts(&self) -> Vec<Prompt> {
+        self.inner
+            .prompts
+            .iter()
+            .map(|prompt| Prompt {
+                name: prompt.name.clone(),
+                description: prompt.description.clone(),
+                arguments: (!prompt.arguments.is_empty()).then(|| {
+                    prompt
+                        .arguments
+                        .iter()
+                        .map(|argument| PromptArgument {
+                            name: argument.name.clone(),
+                            description: argument.description.clone(),
+                            required: Some(argument.required),
+                        })
+                        .collect()
+                }),
+            })
+            .collect()
+    }
+
+    /// Render a prompt; optional arguments that weren't given become empty strings.
+    pub fn get_prompt(
+        &self,
+        name: &str,
+        arguments: Option<&JsonObject>,
+    ) -> Result<GetPromptResult, McpError> {
+        let prompt = self
+            .inner
+            .prompts
+            .iter()
+            .find(|p| p.name == name)
+            .ok_or_else(|| McpError::invalid_params("prompt not found", None))?;
+
+        let mut values = HashMap::new();
+        for argument in &prompt.arguments {
+            let value = arguments
+                .and_then(|arguments| arguments.get(&argument.name)?.as_str())
+                .map(str::to_string);
+            let value = match value {
+                Some(value) => value,
+                None if argument.required => {
+                    return Err(McpError::invalid_params(
+                        format!("No {} provided to {}", argument.name, prompt.name),
+                        None,
+                    ));
+                }
+                None => String::new(),
+            };
+            values.insert(argument.name.as_str(), value);
+        }
+        let text = fill(&prompt.template, &values);
+
+        Ok(GetPromptResult {
+            description: prompt.description.clone(),
+            messages: vec![PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::text(text),
+            }],
+        })
+    }
+}
+
+/// Replace each `{name}` in `template` with its value in one pass, so text
+/// inserted for one argument is never taken for another placeholder.
+/// Placeholders without a value are left as they are.
+fn fill(template: &str, values: &HashMap<&str, String>) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let value = after
+            .find('}')
+            .and_then(|end| Some((values.get(&after[..end])?, end)));
+        match value {
+            Some((value, end)) => {
+                text.push_str(value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                text.push('{');
+                rest = after;
+            }
+        }
+    }
+    text.push_str(rest);
+    text
+}
+
+/// Which of the advertised lists a catalog swap changed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListChanges {
+    pub tools: bool,
+    pub resources: bool,
+    pub prompts: bool,
+}
+
+impl ListChanges {
+    pub fn any(self) -> bool {
+        self.tools || self.resources || self.prompts
+    }
+}
+
+/// The catalog every session reads from. Clones share it, so replacing it
+/// switches all sessions over at once, between two requests.
+#[derive(Debug, Clone)]
+pub struct LiveCatalog {
+    current: Arc<RwLock<Catalog>>,
+    changes: broadcast::Sender<ListChanges>,
+}
+
+impl Default for LiveCatalog {
+    fn default() -> Self {
+        Self::new(Catalog::default())
+    }
+}
+
+impl LiveCatalog {
+    pub fn new(catalog: Catalog) -> Self {
+        let (changes, _) = broadcast::channel(16);
+        Self {
+            current: Arc::new(RwLock::new(catalog)),
+            changes,
+        }
+    }
+
+    /// Snapshot of the catalog; a request should use one snapshot throughout.
+    pub fn current(&self) -> Catalog {
+        self.current.read().expect("catalog lock poisoned").clone()
+    }
+
+    /// Swap in `catalog` and tell every session which lists changed.
+    pub fn replace(&self, catalog: Catalog) -> ListChanges {
+        let mut current = self.current.write().expect("catalog lock poisoned");
+        let changes = ListChanges {
+            tools: current.inner.tools != catalog.inner.tools,
+            resources: current.inner.resources != catalog.inner.resources
+                || current.inner.files != catalog.inner.files,
+            prompts: current.inner.prompts != catalog.inner.prompts,
+        };
+        *current = catalog;
+        drop(current);
+        if changes.any() {
+            // No receivers just means no session is connected
+            let _ = self.changes.send(changes);
+        }
+        changes
+    }
+
+    /// Tell every session the resource list changed without swapping the
+    /// catalog, e.g. because a counter or memo was created or deleted.
+    pub fn resources_changed(&self) {
+        let _ = self.changes.send(ListChanges {
+            resources: true,
+            ..ListChanges::default()
+        });
+    }
+
+    /// Forward list changes to `peer` as `notifications/*/list_changed`
+    /// until the returned guard is dropped or the peer goes away.
+    pub fn notify(&self, peer: Peer<RoleServer>) -> DropGuard {
+        let ct = CancellationToken::new();
+        tokio::spawn(notify::forward(
+            self.changes.subscribe(),
+            peer,
+            ct.clone(),
+            list_changed,
+        ));
+        ct.drop_guard()
+    }
+}
+
+/// The `notifications/*/list_changed` for `changed`; after missed changes, every list.
+fn list_changed(changed: Option<ListChanges>) -> Vec<ServerNotification> {
+    let changed = changed.unwrap_or(ListChanges {
+        tools: true,
+        resources: true,
+        prompts: true,
+    });
+    let mut notifications = Vec::new();
+    if changed.tools {
+        notifications.push(ServerNotification::ToolListChangedNotification(
+            ToolListChangedNotification {
+                method: Default::default(),
+            },
+        ));
+    }
+    if changed.resources {
+        notifications.push(ServerNotification::ResourceListChangedNotification(
+            ResourceListChangedNotification {
+                method: Default::default(),
+            },
+        ));
+    }
+    if changed.prompts {
+        notifications.push(ServerNotification::PromptListChangedNotification(
+            PromptListChangedNotification {
+                method: Default::default(),
+            },
+        ));
+    }
+    notifications
+}
+
+/// Names inside `{...}` in a prompt template.
+fn placeholders(template: &str) -> Result<Vec<&str>, String> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("unclosed {{ in prompt template {template:?}"))?;
+        names.push(&rest[start + 1..start + end]);
+        rest = &rest[start + end + 1..];
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&'static str, &str)]) -> HashMap<&'static str, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (*name, value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn fills_each_placeholder() {
+        let values = values(&[("topic", "rust"), ("tone", "dry")]);
+        assert_eq!(
+            fill("Write about {topic} in a {tone} tone, {topic}!", &values),
+            "Write about rust in a dry tone, rust!"
+        );
+    }
+
+    #[test]
+    fn inserted_values_are_not_expanded_again() {
+        let values = values(&[("topic", "{tone}"), ("tone", "dry")]);
+        assert_eq!(fill("{topic} / {tone}", &values), "{tone} / dry");
+    }
+
+    #[test]
+    fn leaves_unknown_and_unclosed_placeholders() {
+        let values = values(&[("topic", "rust")]);
+        assert_eq!(fill("{other} {topic", &values), "{other} {topic");
+        assert_eq!(fill("{{topic}}", &values), "{rust}");
+        assert_eq!(fill("", &values), "");
+    }
+}
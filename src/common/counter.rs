@@ -1,61 +1,359 @@
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 
 use rmcp::{
-    Error as McpError, RoleServer, ServerHandler, const_string, model::*, schemars,
-    service::RequestContext, tool,
+    Error as McpError, Peer, RoleServer, ServerHandler, const_string,
+    handler::server::tool::ToolCallContext, model::*, schemars, service::RequestContext, tool,
 };
 use serde_json::json;
-use tokio::sync::Mutex;
+use tokio_util::sync::DropGuard;
+
+use super::catalog::LiveCatalog;
+use super::files::{FILE_TEMPLATE, FileError};
+use super::memos::{MEMO_TEMPLATE, Memo, MemoStore, memo_uri};
+use super::pagination::Paginator;
+use super::store::{CounterStore, DEFAULT_COUNTER};
+use super::subscriptions::Subscriptions;
+use super::template::{Router, UriTemplate, Variables};
+use crate::status;
+
+static COUNTER_TEMPLATE: LazyLock<UriTemplate> =
+    LazyLock::new(|| UriTemplate::parse("counter://{name}").expect("valid URI template"));
+
+/// URI under which the named counter is exposed as a resource.
+pub fn counter_uri(name: &str) -> String {
+    COUNTER_TEMPLATE.expand(&Variables::from([("name".to_string(), name.to_string())]))
+}
+
+/// The kinds of parameterized resource a URI can name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceRoute {
+    /// `counter://{name}`
+    Counter,
+    /// `file:///{+path}`, inside the configured file roots
+    File,
+    /// `memo://{id}`
+    Memo,
+}
+
+impl ResourceRoute {
+    fn describe(self) -> (&'static str, &'static str) {
+        match self {
+            Self::Counter => ("counter", "Current value of the named counter"),
+            Self::File => ("file", "A file under one of the server's file roots"),
+            Self::Memo => ("memo", "A memo written with the memo tools, as JSON"),
+        }
+    }
+}
+
+/// Templates for the resources that aren't listed one by one in the catalog.
+pub fn resource_router() -> &'static Router<ResourceRoute> {
+    static ROUTER: LazyLock<Router<ResourceRoute>> = LazyLock::new(|| {
+        Router::default()
+            .route(COUNTER_TEMPLATE.clone(), ResourceRoute::Counter)
+            .route(FILE_TEMPLATE.clone(), ResourceRoute::File)
+            .route(MEMO_TEMPLATE.clone(), ResourceRoute::Memo)
+    });
+    &ROUTER
+}
 
 #[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
 pub struct StructRequest {
-    pub a: i32,
-    pub b: i32,
+    pub a: i64,
+    pub b: i64,
 }
 
+/// One MCP session's view of a [`CounterStore`] and a [`MemoStore`].
+///
+/// Build a fresh `Counter` per session; clones share resource subscriptions.
+/// Static resources, prompts and the set of callable tools come from its
+/// [`LiveCatalog`], and the session is told whenever that catalog changes.
 #[derive(Clone)]
 pub struct Counter {
-    counter: Arc<Mutex<i32>>,
+    store: CounterStore,
+    memos: MemoStore,
+    /// Recorded as the author of memos this session creates
+    session_id: String,
+    catalog: LiveCatalog,
+    pages: Paginator,
+    subscriptions: Subscriptions,
+    peer: Option<Peer<RoleServer>>,
+    /// Stops list change notifications once the session's service is dropped
+    _list_changes: Option<Arc<DropGuard>>,
 }
 #[tool(tool_box)]
 impl Counter {
     #[allow(dead_code)]
     pub fn new() -> Self {
+        Self::with_store(CounterStore::new())
+    }
+
+    pub fn with_store(store: CounterStore) -> Self {
         Self {
-            counter: Arc::new(Mutex::new(0)),
+            store,
+            memos: MemoStore::new(),
+            session_id: "local".to_string(),
+            catalog: LiveCatalog::default(),
+            pages: Paginator::default(),
+            subscriptions: Subscriptions::default(),
+            peer: None,
+            _list_changes: None,
         }
     }
 
+    pub fn with_catalog(mut self, catalog: LiveCatalog) -> Self {
+        self.catalog = catalog;
+        self
+    }
+
+    pub fn with_memos(mut self, memos: MemoStore, session_id: String) -> Self {
+        self.memos = memos;
+        self.session_id = session_id;
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.pages = Paginator::new(page_size);
+        self
+    }
+
+    /// Names of every tool this service exposes.
+    pub fn tool_names() -> Vec<String> {
+        Self::tool_box()
+            .list()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect()
+    }
+
     fn _create_resource_text(&self, uri: &str, name: &str) -> Resource {
         RawResource::new(uri, name.to_string()).no_annotation()
     }
 
-    #[tool(description = "Increment the counter by 1")]
-    async fn increment(&self) -> Result<CallToolResult, McpError> {
-        let mut counter = self.counter.lock().await;
-        *counter += 1;
+    #[tool(description = "Increment a counter by 1")]
+    async fn increment(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Counter name (defaults to \"default\")")]
+        name: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let name = name.as_deref().unwrap_or(DEFAULT_COUNTER);
+        let value = self.store.add(name, 1).await?;
+        Ok(CallToolResult::success(vec![Content::text(
+            value.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Decrement a counter by 1")]
+    async fn decrement(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Counter name (defaults to \"default\")")]
+        name: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let name = name.as_deref().unwrap_or(DEFAULT_COUNTER);
+        let value = self.store.add(name, -1).await?;
+        Ok(CallToolResult::success(vec![Content::text(
+            value.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Add an arbitrary (possibly negative) amount to a counter")]
+    async fn increment_by(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Amount to add to the counter")]
+        delta: i64,
+        #[tool(param)]
+        #[schemars(description = "Counter name (defaults to \"default\")")]
+        name: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let name = name.as_deref().unwrap_or(DEFAULT_COUNTER);
+        let value = self.store.add(name, delta).await?;
+        Ok(CallToolResult::success(vec![Content::text(
+            value.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Set a counter to a specific value")]
+    async fn set(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "New value for the counter")]
+        value: i64,
+        #[tool(param)]
+        #[schemars(description = "Counter name (defaults to \"default\")")]
+        name: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let name = name.as_deref().unwrap_or(DEFAULT_COUNTER);
+        let value = self.store.set(name, value).await?;
         Ok(CallToolResult::success(vec![Content::text(
-            counter.to_string(),
+            value.to_string(),
         )]))
     }
 
-    #[tool(description = "Decrement the counter by 1")]
-    async fn decrement(&self) -> Result<CallToolResult, McpError> {
-        let mut counter = self.counter.lock().await;
-        *counter -= 1;
+    #[tool(description = "Reset a counter to 0")]
+    async fn reset(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Counter name (defaults to \"default\")")]
+        name: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let name = name.as_deref().unwrap_or(DEFAULT_COUNTER);
+        let value = self.store.set(name, 0).await?;
         Ok(CallToolResult::success(vec![Content::text(
-            counter.to_string(),
+            value.to_string(),
         )]))
     }
 
-    #[tool(description = "Get the current counter value")]
-    async fn get_value(&self) -> Result<CallToolResult, McpError> {
-        let counter = self.counter.lock().await;
+    #[tool(
+        description = "Atomically set a counter to `new` only if it currently equals `expected`. Returns whether the swap happened and the counter's current value."
+    )]
+    async fn compare_and_swap(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Value the counter must currently hold")]
+        expected: i64,
+        #[tool(param)]
+        #[schemars(description = "Value to store if the counter matches `expected`")]
+        new: i64,
+        #[tool(param)]
+        #[schemars(description = "Counter name (defaults to \"default\")")]
+        name: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let name = name.as_deref().unwrap_or(DEFAULT_COUNTER);
+        let (swapped, value) = self.store.compare_and_swap(name, expected, new).await?;
+        Ok(CallToolResult::success(vec![Content::json(json!({
+            "swapped": swapped,
+            "value": value,
+        }))?]))
+    }
+
+    #[tool(description = "Get the current value of a counter")]
+    async fn get_value(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Counter name (defaults to \"default\")")]
+        name: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let name = name.as_deref().unwrap_or(DEFAULT_COUNTER);
+        let value = self.store.get(name).await?;
         Ok(CallToolResult::success(vec![Content::text(
-            counter.to_string(),
+            value.to_string(),
         )]))
     }
 
+    #[tool(description = "Create a new named counter starting at 0")]
+    async fn create_counter(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Name of the counter to create")]
+        name: String,
+    ) -> Result<CallToolResult, McpError> {
+        self.store.create(&name).await?;
+        // Each counter is listed as a resource too
+        self.catalog.resources_changed();
+        Ok(CallToolResult::success(vec![Content::text("0")]))
+    }
+
+    #[tool(description = "List all counters and their current values")]
+    async fn list_counters(&self) -> Result<CallToolResult, McpError> {
+        let counters = self.store.list().await;
+        Ok(CallToolResult::success(vec![Content::json(counters)?]))
+    }
+
+    #[tool(description = "Delete a named counter, returning its final value")]
+    async fn delete_counter(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Name of the counter to delete")]
+        name: String,
+    ) -> Result<CallToolResult, McpError> {
+        let value = self.store.delete(&name).await?;
+        self.catalog.resources_changed();
+        Ok(CallToolResult::success(vec![Content::text(
+            value.to_string(),
+        )]))
+    }
+
+    #[tool(description = "Write a new memo; returns it with its id and memo:// URI")]
+    async fn create_memo(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Short title for the memo")]
+        title: String,
+        #[tool(param)]
+        #[schemars(description = "Text of the memo (defaults to empty)")]
+        body: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let memo = self
+            .memos
+            .create(
+                &title,
+                body.as_deref().unwrap_or_default(),
+                &self.session_id,
+            )
+            .await?;
+        self.memo_changed(memo)
+    }
+
+    #[tool(description = "Add text to the end of a memo, on a new line")]
+    async fn append_memo(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Id of the memo")]
+        id: u64,
+        #[tool(param)]
+        #[schemars(description = "Text to append")]
+        text: String,
+    ) -> Result<CallToolResult, McpError> {
+        let memo = self.memos.append(id, &text).await?;
+        self.memo_changed(memo)
+    }
+
+    #[tool(description = "Replace a memo's title, body, or both")]
+    async fn update_memo(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Id of the memo")]
+        id: u64,
+        #[tool(param)]
+        #[schemars(description = "New title (unchanged if omitted)")]
+        title: Option<String>,
+        #[tool(param)]
+        #[schemars(description = "New body (unchanged if omitted)")]
+        body: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let memo = self.memos.replace(id, title, body).await?;
+        self.memo_changed(memo)
+    }
+
+    #[tool(description = "Delete a memo, returning it as it was")]
+    async fn delete_memo(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Id of the memo to delete")]
+        id: u64,
+    ) -> Result<CallToolResult, McpError> {
+        let memo = self.memos.delete(id).await?;
+        self.memo_changed(memo)
+    }
+
+    #[tool(
+        description = "Find memos whose title or body contains the query, ignoring case. Returns each match's id, URI, title, author and, for body matches, the first matching line."
+    )]
+    async fn search_memos(
+        &self,
+        #[tool(param)]
+        #[schemars(description = "Text to look for (lists every memo if omitted)")]
+        query: Option<String>,
+    ) -> Result<CallToolResult, McpError> {
+        let found = self
+            .memos
+            .search(query.as_deref().unwrap_or_default())
+            .await;
+        Ok(CallToolResult::success(vec![Content::json(found)?]))
+    }
+
     #[tool(description = "Say hello to the client")]
     fn say_hello(&self) -> Result<CallToolResult, McpError> {
         Ok(CallToolResult::success(vec![Content::text("hello")]))
@@ -76,38 +374,88 @@ impl Counter {
         &self,
         #[tool(aggr)] StructRequest { a, b }: StructRequest,
     ) -> Result<CallToolResult, McpError> {
+        let total = self.store.overflow().add(a, b)?;
         Ok(CallToolResult::success(vec![Content::text(
-            (a + b).to_string(),
+            total.to_string(),
         )]))
     }
 }
 const_string!(Echo = "echo");
-#[tool(tool_box)]
+impl Counter {
+    /// Tell every session its resource list is stale and return `memo` to the caller.
+    fn memo_changed(&self, memo: Memo) -> Result<CallToolResult, McpError> {
+        self.catalog.resources_changed();
+        Ok(CallToolResult::success(vec![Content::json(memo_json(
+            &memo,
+        ))?]))
+    }
+}
+
+/// A memo as tools and `memo://` reads return it, with its URI alongside.
+fn memo_json(memo: &Memo) -> serde_json::Value {
+    let mut value = json!(memo);
+    value["uri"] = json!(memo_uri(memo.id));
+    value
+}
+
 impl ServerHandler for Counter {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::V_2024_11_05,
             capabilities: ServerCapabilities::builder()
                 .enable_prompts()
+                .enable_prompts_list_changed()
                 .enable_resources()
+                .enable_resources_list_changed()
+                .enable_resources_subscribe()
                 .enable_tools()
+                .enable_tool_list_changed()
                 .build(),
-            server_info: Implementation::from_build_env(),
-            instructions: Some("This server provides a counter tool that can increment and decrement values. The counter starts at 0 and can be modified using the 'increment' and 'decrement' tools. Use 'get_value' to check the current count.".to_string()),
+            server_info: status::implementation(),
+            instructions: Some("This server provides named counters that can be incremented and decremented. A counter called 'default' always exists and is used when no name is given. Create more with 'create_counter', modify them with 'increment', 'decrement', 'increment_by', 'set' and 'reset', coordinate concurrent updates with 'compare_and_swap', check them with 'get_value', and manage them with 'list_counters' and 'delete_counter'. Each counter is also readable as the resource 'counter://<name>', which clients can subscribe to for change notifications. Notes shared between sessions can be kept with 'create_memo', 'append_memo', 'update_memo' and 'delete_memo', found with 'search_memos', and read as the resource 'memo://<id>'.".to_string()),
         }
     }
 
+    fn get_peer(&self) -> Option<Peer<RoleServer>> {
+        self.peer.clone()
+    }
+
+    fn set_peer(&mut self, peer: Peer<RoleServer>) {
+        self._list_changes = Some(Arc::new(self.catalog.notify(peer.clone())));
+        self.peer = Some(peer);
+    }
+
     async fn list_resources(
         &self,
-        _request: Option<PaginatedRequestParam>,
+        request: Option<PaginatedRequestParam>,
         _: RequestContext<RoleServer>,
     ) -> Result<ListResourcesResult, McpError> {
+        let catalog = self.catalog.current();
+        let mut resources = catalog.list_resources();
+        // Only the first page walks the file roots; later ones page through that walk
+        let first_page = request
+            .as_ref()
+            .is_none_or(|request| request.cursor.is_none());
+        resources.extend(catalog.files().list(first_page).await.iter().cloned());
+        for memo in self.memos.list().await {
+            let mut raw = RawResource::new(memo_uri(memo.id), memo.title);
+            raw.description = Some(format!(
+                "Memo by {}, updated {}",
+                memo.author_session,
+                memo.updated_at.to_rfc3339()
+            ));
+            raw.mime_type = Some("application/json".to_string());
+            resources.push(raw.no_annotation());
+        }
+        for name in self.store.list().await.keys() {
+            resources.push(self._create_resource_text(&counter_uri(name), name));
+        }
+        let page = self
+            .pages
+            .page("resources", resources, |r| &r.raw.uri, request)?;
         Ok(ListResourcesResult {
-            resources: vec![
-                self._create_resource_text("str:////Users/to/some/path/", "cwd"),
-                self._create_resource_text("memo://insights", "memo-name"),
-            ],
-            next_cursor: None,
+            resources: page.items,
+            next_cursor: page.next_cursor,
         })
     }
 
@@ -116,44 +464,109 @@ impl ServerHandler for Counter {
         ReadResourceRequestParam { uri }: ReadResourceRequestParam,
         _: RequestContext<RoleServer>,
     ) -> Result<ReadResourceResult, McpError> {
-        match uri.as_str() {
-            "str:////Users/to/some/path/" => {
-                let cwd = "/Users/to/some/path/";
+        let catalog = self.catalog.current();
+        if let Some(contents) = catalog.read_resource(&uri) {
+            return Ok(ReadResourceResult {
+                contents: vec![contents],
+            });
+        }
+        let (template, route, variables) = resource_router()
+            .resolve(&uri)
+            .ok_or_else(|| not_found(&uri))?;
+        // Answer with the URI as we'd write it, e.g. with spaces escaped
+        let uri = template.expand(&variables);
+        match route {
+            ResourceRoute::Counter => {
+                let value = self
+                    .store
+                    .get(&variables["name"])
+                    .await
+                    .map_err(|_| not_found(&uri))?;
                 Ok(ReadResourceResult {
-                    contents: vec![ResourceContents::text(cwd, uri)],
+                    contents: vec![ResourceContents::text(value.to_string(), uri)],
                 })
             }
-            "memo://insights" => {
-                let memo = "Business Intelligence Memo\n\nAnalysis has revealed 5 key insights ...";
+            ResourceRoute::File => {
+                let contents = catalog
+                    .files()
+                    .read(&variables["path"], uri.clone())
+                    .await
+                    .map_err(|e| file_error(&uri, e))?;
                 Ok(ReadResourceResult {
-                    contents: vec![ResourceContents::text(memo, uri)],
+                    contents: vec![contents],
                 })
             }
-            _ => Err(McpError::resource_not_found(
-                "resource_not_found",
-                Some(json!({
-                    "uri": uri
-                })),
-            )),
+            ResourceRoute::Memo => {
+                let memo = match variables["id"].parse() {
+                    Ok(id) => self.memos.get(id).await,
+                    Err(_) => None,
+                }
+                .ok_or_else(|| not_found(&uri))?;
+                Ok(ReadResourceResult {
+                    contents: vec![ResourceContents::TextResourceContents {
+                        uri,
+                        mime_type: Some("application/json".to_string()),
+                        text: serde_json::to_string_pretty(&memo_json(&memo))
+                            .map_err(|e| McpError::internal_error(e.to_string(), None))?,
+                    }],
+                })
+            }
+        }
+    }
+
+    async fn subscribe(
+        &self,
+        SubscribeRequestParam { uri }: SubscribeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        let (template, route, variables) = resource_router()
+            .resolve(&uri)
+            .ok_or_else(|| not_found(&uri))?;
+        let uri = template.expand(&variables);
+        match route {
+            ResourceRoute::Counter => {
+                self.store
+                    .get(&variables["name"])
+                    .await
+                    .map_err(|_| not_found(&uri))?;
+                self.subscriptions
+                    .subscribe(uri, context.peer, self.store.changes())
+                    .await;
+            }
+            ResourceRoute::File | ResourceRoute::Memo => {
+                return Err(McpError::invalid_params(
+                    "only counter resources support subscriptions",
+                    Some(json!({ "uri": uri })),
+                ));
+            }
         }
+        Ok(())
+    }
+
+    async fn unsubscribe(
+        &self,
+        UnsubscribeRequestParam { uri }: UnsubscribeRequestParam,
+        _: RequestContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        // Match the form subscribe stored, e.g. with spaces escaped
+        let uri = match resource_router().resolve(&uri) {
+            Some((template, _, variables)) => template.expand(&variables),
+            None => uri,
+        };
+        self.subscriptions.unsubscribe(&uri).await;
+        Ok(())
     }
 
     async fn list_prompts(
         &self,
-        _request: Option<PaginatedRequestParam>,
+        request: Option<PaginatedRequestParam>,
         _: RequestContext<RoleServer>,
     ) -> Result<ListPromptsResult, McpError> {
+        let prompts = self.catalog.current().list_prompts();
+        let page = self.pages.page("prompts", prompts, |p| &p.name, request)?;
         Ok(ListPromptsResult {
-            next_cursor: None,
-            prompts: vec![Prompt::new(
-                "example_prompt",
-                Some("This is an example prompt that takes one required argument, message"),
-                Some(vec![PromptArgument {
-                    name: "message".to_string(),
-                    description: Some("A message to put in the prompt".to_string()),
-                    required: Some(true),
-                }]),
-            )],
+            next_cursor: page.next_cursor,
+            prompts: page.items,
         })
     }
 
@@ -162,36 +575,217 @@ impl ServerHandler for Counter {
         GetPromptRequestParam { name, arguments }: GetPromptRequestParam,
         _: RequestContext<RoleServer>,
     ) -> Result<GetPromptResult, McpError> {
-        match name.as_str() {
-            "example_prompt" => {
-                let message = arguments
-                    .and_then(|json| json.get("message")?.as_str().map(|s| s.to_string()))
-                    .ok_or_else(|| {
-                        McpError::invalid_params("No message provided to example_prompt", None)
-                    })?;
-
-                let prompt =
-                    format!("This is an example prompt with your message here: '{message}'");
-                Ok(GetPromptResult {
-                    description: None,
-                    messages: vec![PromptMessage {
-                        role: PromptMessageRole::User,
-                        content: PromptMessageContent::text(prompt),
-                    }],
-                })
-            }
-            _ => Err(McpError::invalid_params("prompt not found", None)),
+        self.catalog.current().get_prompt(&name, arguments.as_ref())
+    }
+
+    async fn list_tools(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, McpError> {
+        let catalog = self.catalog.current();
+        let tools = Self::tool_box()
+            .list()
+            .into_iter()
+            .filter(|tool| catalog.tool_enabled(&tool.name))
+            .collect();
+        let page = self.pages.page("tools", tools, |t| &t.name, request)?;
+        Ok(ListToolsResult {
+            next_cursor: page.next_cursor,
+            tools: page.items,
+        })
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        // Disabled tools look exactly like ones that don't exist
+        if !self.catalog.current().tool_enabled(&request.name) {
+            return Err(McpError::invalid_params("tool not found", None));
         }
+        let context = ToolCallContext::new(self, request, context);
+        Self::tool_box().call(context).await
     }
 
     async fn list_resource_templates(
         &self,
-        _request: Option<PaginatedRequestParam>,
+        request: Option<PaginatedRequestParam>,
         _: RequestContext<RoleServer>,
     ) -> Result<ListResourceTemplatesResult, McpError> {
+        let templates = resource_router()
+            .routes()
+            .map(|(template, route)| {
+                let (name, description) = route.describe();
+                RawResourceTemplate {
+                    uri_template: template.to_string(),
+                    name: name.to_string(),
+                    description: Some(description.to_string()),
+                    mime_type: None,
+                }
+                .no_annotation()
+            })
+            .collect();
+        let page = self.pages.page(
+            "resource_templates",
+            templates,
+            |t| &t.raw.uri_template,
+            request,
+        )?;
         Ok(ListResourceTemplatesResult {
-            next_cursor: None,
-            resource_templates: Vec::new(),
+            next_cursor: page.next_cursor,
+            resource_templates: page.items,
         })
     }
 }
+
+fn not_found(uri: &str) -> McpError {
+    McpError::resource_not_found(
+        "resource_not_found",
+        Some(json!({
+            "uri": uri
+        })),
+    )
+}
+
+fn file_error(uri: &str, e: FileError) -> McpError {
+    match e {
+        FileError::NotFound => not_found(uri),
+        FileError::TooLarge { size, max_size } => McpError::invalid_params(
+            "file is larger than the server's file size limit",
+            Some(json!({ "uri": uri, "size": size, "max_size": max_size })),
+        ),
+        FileError::Io(e) => McpError::internal_error(
+            format!("failed to read file: {e}"),
+            Some(json!({ "uri": uri })),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rmcp::ServiceExt;
+    use rmcp::service::{RoleClient, RunningService};
+
+    use super::*;
+    use crate::common::catalog::{Catalog, PromptDef};
+    use crate::common::files::FileRoots;
+
+    /// Serve `counter` over an in-memory pipe and connect a client to it.
+    async fn connect(counter: Counter) -> RunningService<RoleClient, ()> {
+        let (server, client) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let server = counter.serve(server).await?;
+            server.waiting().await?;
+            anyhow::Ok(())
+        });
+        ().serve(client).await.expect("initialize")
+    }
+
+    /// Every item of a `*/list` request, following cursors, and how many pages it took.
+    macro_rules! walk {
+        ($client:expr, $method:ident, $items:ident) => {{
+            let mut items = Vec::new();
+            let mut pages = 0;
+            let mut cursor = None;
+            loop {
+                let page = $client
+                    .$method(Some(PaginatedRequestParam { cursor }))
+                    .await
+                    .expect("list page");
+                pages += 1;
+                items.extend(page.$items);
+                cursor = page.next_cursor;
+                if cursor.is_none() {
+                    break;
+                }
+            }
+            (items, pages)
+        }};
+    }
+
+    fn prompt(name: &str) -> PromptDef {
+        PromptDef {
+            name: name.to_string(),
+            description: None,
+            template: "text".to_string(),
+            arguments: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn list_tools_pages_by_the_configured_size() {
+        let client = connect(Counter::new().with_page_size(4)).await;
+        let first = client.list_tools(None).await.unwrap();
+        assert_eq!(first.tools.len(), 4);
+        assert!(first.next_cursor.is_some());
+
+        let (tools, pages) = walk!(client, list_tools, tools);
+        let mut expected = Counter::tool_names();
+        expected.sort();
+        let names: Vec<_> = tools.iter().map(|tool| tool.name.to_string()).collect();
+        assert_eq!(names, expected);
+        assert_eq!(pages, expected.len().div_ceil(4));
+    }
+
+    #[tokio::test]
+    async fn list_resources_pages_through_counters() {
+        let store = CounterStore::new();
+        for name in ["a", "b", "c", "d", "e"] {
+            store.create(name).await.unwrap();
+        }
+        let client = connect(Counter::with_store(store).with_page_size(2)).await;
+        let (resources, pages) = walk!(client, list_resources, resources);
+        let uris: Vec<_> = resources.iter().map(|r| r.raw.uri.as_str()).collect();
+        assert_eq!(
+            uris,
+            [
+                "counter://a",
+                "counter://b",
+                "counter://c",
+                "counter://d",
+                "counter://default",
+                "counter://e"
+            ]
+        );
+        assert_eq!(pages, 3);
+    }
+
+    #[tokio::test]
+    async fn list_prompts_and_templates_page_by_the_configured_size() {
+        let catalog = Catalog::new(
+            Vec::new(),
+            vec![prompt("c"), prompt("a"), prompt("b")],
+            None,
+            FileRoots::default(),
+        )
+        .unwrap();
+        let counter = Counter::new()
+            .with_catalog(LiveCatalog::new(catalog))
+            .with_page_size(1);
+        let client = connect(counter).await;
+
+        let (prompts, pages) = walk!(client, list_prompts, prompts);
+        let names: Vec<_> = prompts.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+        assert_eq!(pages, 3);
+
+        let (templates, pages) = walk!(client, list_resource_templates, resource_templates);
+        assert_eq!(templates.len(), resource_router().routes().count());
+        assert_eq!(pages, templates.len());
+    }
+
+    #[tokio::test]
+    async fn cursors_only_work_on_their_own_list() {
+        let client = connect(Counter::new().with_page_size(1)).await;
+        let tools = client.list_tools(None).await.unwrap();
+        let error = client
+            .list_prompts(Some(PaginatedRequestParam {
+                cursor: tools.next_cursor,
+            }))
+            .await
+            .expect_err("tools cursor on prompts");
+        assert!(error.to_string().contains("invalid cursor"), "{error}");
+    }
+}
@@ -0,0 +1,471 @@
+use std::fs::{File, Metadata};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use rmcp::model::*;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tracing::{debug, warn};
+
+use super::template::{UriTemplate, Variables};
+
+/// Files larger than this aren't served unless configured otherwise.
+pub const DEFAULT_FILE_MAX_SIZE: u64 = 1024 * 1024;
+
+/// Stop walking the roots after this many files, so a root pointed at a
+/// huge tree can't stall `resources/list`.
+const LIST_LIMIT: usize = 10_000;
+
+/// How long later pages of a `resources/list` walk reuse the listing its
+/// first page made, rather than walking the roots again.
+const LISTING_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// How much of a file read is looked at to guess its type when the extension doesn't say.
+const SNIFF_LEN: usize = 512;
+
+pub static FILE_TEMPLATE: LazyLock<UriTemplate> =
+    LazyLock::new(|| UriTemplate::parse("file:///{+path}").expect("valid URI template"));
+
+/// `file://` URI of an absolute path; paths that aren't UTF-8 have none.
+pub fn file_uri(path: &Path) -> Option<String> {
+    let path = path.to_str()?.strip_prefix('/')?;
+    Some(FILE_TEMPLATE.expand(&Variables::from([("path".to_string(), path.to_string())])))
+}
+
+/// Why a file couldn't be served.
+#[derive(Debug)]
+pub enum FileError {
+    /// Missing, not a regular file, or outside every root; deliberately indistinguishable
+    NotFound,
+    TooLarge {
+        size: u64,
+        max_size: u64,
+    },
+    Io(io::Error),
+}
+
+/// Directories whose files are served as `file://` resources.
+///
+/// Roots are canonicalized up front, and every requested path is
+/// canonicalized before use, so `..` segments and symlinks can only reach
+/// files that are inside a root once resolved.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileRoots {
+    #[serde(rename = "file_roots")]
+    roots: Vec<PathBuf>,
+    #[serde(rename = "file_max_size")]
+    max_size: u64,
+    #[serde(skip)]
+    listing: Listing,
+}
+
+impl Default for FileRoots {
+    fn default() -> Self {
+        Self {
+            roots: Vec::new(),
+            max_size: DEFAULT_FILE_MAX_SIZE,
+            listing: Listing::default(),
+        }
+    }
+}
+
+/// The last walk of the roots, shared by every clone.
+#[derive(Debug, Clone, Default)]
+struct Listing(Arc<Mutex<Option<Walk>>>);
+
+#[derive(Debug)]
+struct Walk {
+    made: Instant,
+    resources: Arc<Vec<Resource>>,
+}
+
+/// A cache, not configuration, so it never makes two sets of roots differ.
+impl PartialEq for Listing {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl FileRoots {
+    pub fn new(roots: Vec<PathBuf>, max_size: u64) -> Result<Self, String> {
+        let mut canonical = Vec::new();
+        for root in roots {
+            let path = root
+                .canonicalize()
+                .map_err(|e| format!("file root {}: {e}", root.display()))?;
+            if !path.is_dir() {
+                return Err(format!("file root {} is not a directory", root.display()));
+            }
+            if !canonical.contains(&path) {
+                canonical.push(path);
+            }
+        }
+        // A root inside another would list its files twice, under the same URIs
+        let roots = canonical
+            .iter()
+            .filter(|root| {
+                !canonical
+                    .iter()
+                    .any(|other| other != *root && root.starts_with(other))
+            })
+            .cloned()
+            .collect();
+        Ok(Self {
+            roots,
+            max_size,
+            listing: Listing::default(),
+        })
+    }
+
+    /// Every file under the roots.
+    ///
+    /// Walking the roots costs as much as listing everything, so unless
+    /// `refresh` is set, a listing younger than [`LISTING_MAX_AGE`] is reused.
+    pub async fn list(&self, refresh: bool) -> Arc<Vec<Resource>> {
+        if self.roots.is_empty() {
+            return Arc::default();
+        }
+        let mut listing = self.listing.0.lock().await;
+        if !refresh
+            && let Some(walk) = listing.as_ref()
+            && walk.made.elapsed() < LISTING_MAX_AGE
+        {
+            return walk.resources.clone();
+        }
+        let roots = self.clone();
+        let resources = Arc::new(
+            tokio::task::spawn_blocking(move || roots.walk())
+                .await
+                .unwrap_or_else(|e| {
+                    warn!("Listing file resources failed: {:?}", e);
+                    Vec::new()
+                }),
+        );
+        *listing = Some(Walk {
+            made: Instant::now(),
+            resources: resources.clone(),
+        });
+        resources
+    }
+
+    /// Read the file named by the `path` variable of a `file:///{+path}` URI.
+    pub async fn read(&self, path: &str, uri: String) -> Result<ResourceContents, FileError> {
+        let roots = self.clone();
+        let path = Path::new("/").join(path);
+        tokio::task::spawn_blocking(move || roots.read_blocking(&path, uri))
+            .await
+            .map_err(|e| FileError::Io(io::Error::other(e)))?
+    }
+
+    /// The canonical form of `path` if it is a regular file inside a root.
+    fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        let canonical = path.canonicalize().ok()?;
+        let inside = self.roots.iter().any(|root| canonical.starts_with(root));
+        (inside && canonical.is_file()).then_some(canonical)
+    }
+
+    fn read_blocking(&self, path: &Path, uri: String) -> Result<ResourceContents, FileError> {
+        let path = self.resolve(path).ok_or(FileError::NotFound)?;
+        let mut file = File::open(&path).map_err(FileError::Io)?;
+        let metadata = file.metadata().map_err(FileError::Io)?;
+        if !is_same_file(&metadata, &path) {
+            return Err(FileError::NotFound);
+        }
+        let size = metadata.len();
+        if size > self.max_size {
+            return Err(FileError::TooLarge {
+                size,
+                max_size: self.max_size,
+            });
+        }
+        // The file may have grown since we looked
+        let mut bytes = Vec::new();
+        file.by_ref()
+            .take(self.max_size + 1)
+            .read_to_end(&mut bytes)
+            .map_err(FileError::Io)?;
+        if bytes.len() as u64 > self.max_size {
+            return Err(FileError::TooLarge {
+                size: bytes.len() as u64,
+                max_size: self.max_size,
+            });
+        }
+
+        let mime_type = Some(by_extension(&path).unwrap_or_else(|| sniff(&bytes).to_string()));
+        Ok(match String::from_utf8(bytes) {
+            Ok(text) if !text.contains('\0') => ResourceContents::TextResourceContents {
+                uri,
+                mime_type,
+                text,
+            },
+            Ok(text) => ResourceContents::BlobResourceContents {
+                uri,
+                mime_type,
+                blob: BASE64.encode(text),
+            },
+            Err(e) => ResourceContents::BlobResourceContents {
+                uri,
+                mime_type,
+                blob: BASE64.encode(e.into_bytes()),
+            },
+        })
+    }
+
+    /// Regular files under every root, depth first. Symlinks are listed only
+    /// if they resolve to a file inside a root, and never descended into.
+    fn walk(&self) -> Vec<Resource> {
+        let mut resources = Vec::new();
+        for root in &self.roots {
+            let mut pending = vec![root.clone()];
+            while let Some(dir) = pending.pop() {
+                let entries = match std::fs::read_dir(&dir) {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        debug!(dir = %dir.display(), "Skipping unreadable directory: {}", e);
+                        continue;
+                    }
+                };
+                let mut entries: Vec<_> = entries.flatten().map(|entry| entry.path()).collect();
+                entries.sort();
+                let mut subdirs = Vec::new();
+                for path in entries {
+                    let Ok(metadata) = path.symlink_metadata() else {
+                        continue;
+                    };
+                    if metadata.is_dir() {
+                        subdirs.push(path);
+                        continue;
+                    }
+                    let Some(target) = self.resolve(&path) else {
+                        continue;
+                    };
+                    let Some(uri) = file_uri(&path) else {
+                        continue;
+                    };
+                    if resources.len() == LIST_LIMIT {
+                        warn!(
+                            limit = LIST_LIMIT,
+                            "Too many files under the file roots, listing only the first ones"
+                        );
+                        return resources;
+                    }
+                    let name = path.strip_prefix(root).unwrap_or(&path);
+                    let mut raw = RawResource::new(uri, name.display().to_string());
+                    let size = target.metadata().map(|m| m.len()).unwrap_or_default();
+                    raw.size = Some(u32::try_from(size).unwrap_or(u32::MAX));
+                    // Sniffing would open every file on every page, so that waits for a read
+                    raw.mime_type = by_extension(&path);
+                    resources.push(raw.no_annotation());
+                }
+                // Files before subdirectories, each in name order
+                pending.extend(subdirs.into_iter().rev());
+            }
+        }
+        resources
+    }
+}
+
+/// Whether `opened` is still the regular file at the canonical `path`.
+///
+/// A directory on the way could have been swapped for a symlink between
+/// resolving the path and opening it, redirecting the open out of the roots.
+#[cfg(unix)]
+fn is_same_file(opened: &Metadata, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    let unchanged = path.canonicalize().is_ok_and(|canonical| canonical == path);
+    unchanged
+        && opened.is_file()
+        && path
+            .symlink_metadata()
+            .is_ok_and(|current| current.dev() == opened.dev() && current.ino() == opened.ino())
+}
+
+#[cfg(not(unix))]
+fn is_same_file(opened: &Metadata, _path: &Path) -> bool {
+    opened.is_file()
+}
+
+fn by_extension(path: &Path) -> Option<String> {
+    mime_guess::from_path(path)
+        .first()
+        .map(|mime| mime.to_string())
+}
+
+/// Guess from a few well-known signatures, then by whether the content reads as text.
+fn sniff(bytes: &[u8]) -> &'static str {
+    const SIGNATURES: [(&[u8], &str); 7] = [
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF8", "image/gif"),
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\x7fELF", "application/x-executable"),
+    ];
+    if let Some((_, mime)) = SIGNATURES
+        .iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+    {
+        return mime;
+    }
+    let head = &bytes[..bytes.len().min(SNIFF_LEN)];
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => !text.contains('\0'),
+        // A multi-byte character cut off at the end is still text
+        Err(e) => e.error_len().is_none() && !head[..e.valid_up_to()].contains(&0),
+    };
+    if text {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    /// A root holding `inside.txt`, next to a `secret.txt` outside it.
+    fn sandbox(name: &str) -> (TempDir, FileRoots) {
+        let dir = TempDir::new(name);
+        std::fs::create_dir(dir.join("root")).unwrap();
+        std::fs::write(dir.join("root/inside.txt"), "inside").unwrap();
+        std::fs::write(dir.join("secret.txt"), "secret").unwrap();
+        let roots = FileRoots::new(vec![dir.join("root")], DEFAULT_FILE_MAX_SIZE).unwrap();
+        (dir, roots)
+    }
+
+    /// The `path` variable of the `file://` URI for `path`.
+    fn variable(path: &Path) -> String {
+        path.to_str().unwrap().trim_start_matches('/').to_string()
+    }
+
+    async fn read(roots: &FileRoots, path: &Path) -> Result<ResourceContents, FileError> {
+        roots
+            .read(&variable(path), "file:///test".to_string())
+            .await
+    }
+
+    fn text(contents: ResourceContents) -> String {
+        match contents {
+            ResourceContents::TextResourceContents { text, .. } => text,
+            other => panic!("expected text, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn reads_files_inside_a_root() {
+        let (dir, roots) = sandbox("files-inside");
+        let contents = read(&roots, &dir.join("root/inside.txt")).await.unwrap();
+        assert_eq!(text(contents), "inside");
+    }
+
+    #[tokio::test]
+    async fn rejects_dot_dot_traversal() {
+        let (dir, roots) = sandbox("files-traversal");
+        let result = read(&roots, &dir.join("root/../secret.txt")).await;
+        assert!(matches!(result, Err(FileError::NotFound)), "{result:?}");
+    }
+
+    #[tokio::test]
+    async fn rejects_symlinks_out_of_the_root() {
+        let (dir, roots) = sandbox("files-symlink");
+        std::os::unix::fs::symlink(dir.join("secret.txt"), dir.join("root/escape.txt")).unwrap();
+        std::os::unix::fs::symlink(dir.path(), dir.join("root/up")).unwrap();
+
+        let result = read(&roots, &dir.join("root/escape.txt")).await;
+        assert!(matches!(result, Err(FileError::NotFound)), "{result:?}");
+        let result = read(&roots, &dir.join("root/up/secret.txt")).await;
+        assert!(matches!(result, Err(FileError::NotFound)), "{result:?}");
+
+        let listed: Vec<_> = roots
+            .list(true)
+            .await
+            .iter()
+            .map(|r| r.raw.name.clone())
+            .collect();
+        assert_eq!(listed, ["inside.txt"]);
+    }
+
+    #[tokio::test]
+    async fn rejects_files_over_the_size_cap() {
+        let (dir, _) = sandbox("files-size");
+        let roots = FileRoots::new(vec![dir.join("root")], 4).unwrap();
+        let result = read(&roots, &dir.join("root/inside.txt")).await;
+        assert!(
+            matches!(
+                result,
+                Err(FileError::TooLarge {
+                    size: 6,
+                    max_size: 4
+                })
+            ),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn rejects_a_path_that_no_longer_names_the_opened_file() {
+        let (dir, _) = sandbox("files-swap");
+        let path = dir.join("root/inside.txt");
+        let opened = File::open(&path).unwrap().metadata().unwrap();
+        assert!(is_same_file(&opened, &path));
+
+        // Replaced after it was opened, e.g. by a file from elsewhere
+        std::fs::rename(dir.join("secret.txt"), &path).unwrap();
+        assert!(!is_same_file(&opened, &path));
+    }
+
+    #[test]
+    fn rejects_a_path_that_is_not_canonical() {
+        let (dir, _) = sandbox("files-canonical");
+        let path = dir.join("root/inside.txt");
+        let opened = File::open(&path).unwrap().metadata().unwrap();
+        std::os::unix::fs::symlink(&path, dir.join("root/link.txt")).unwrap();
+        assert!(!is_same_file(&opened, &dir.join("root/link.txt")));
+    }
+
+    #[tokio::test]
+    async fn nested_roots_list_each_file_once() {
+        let (dir, _) = sandbox("files-nested");
+        std::fs::create_dir(dir.join("root/sub")).unwrap();
+        std::fs::write(dir.join("root/sub/deep.txt"), "deep").unwrap();
+        let roots = FileRoots::new(
+            vec![
+                dir.join("root/sub"),
+                dir.join("root"),
+                dir.join("root/./sub"),
+            ],
+            DEFAULT_FILE_MAX_SIZE,
+        )
+        .unwrap();
+        let uris: Vec<_> = roots
+            .list(true)
+            .await
+            .iter()
+            .map(|r| r.raw.uri.clone())
+            .collect();
+        let root = dir.join("root").canonicalize().unwrap();
+        assert_eq!(
+            uris,
+            [
+                file_uri(&root.join("inside.txt")).unwrap(),
+                file_uri(&root.join("sub/deep.txt")).unwrap(),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn later_pages_reuse_the_listing() {
+        let (dir, roots) = sandbox("files-listing");
+        assert_eq!(roots.list(true).await.len(), 1);
+        std::fs::write(dir.join("root/new.txt"), "new").unwrap();
+        assert_eq!(roots.list(false).await.len(), 1);
+        assert_eq!(roots.list(true).await.len(), 2);
+    }
+}
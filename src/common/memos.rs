@@ -0,0 +1,318 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock};
+
+use chrono::{DateTime, Utc};
+use rmcp::Error as McpError;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::Mutex;
+use tracing::info;
+
+use super::persist;
+use super::template::{UriTemplate, Variables};
+
+pub static MEMO_TEMPLATE: LazyLock<UriTemplate> =
+    LazyLock::new(|| UriTemplate::parse("memo://{id}").expect("valid URI template"));
+
+/// URI under which a memo is exposed as a resource.
+pub fn memo_uri(id: u64) -> String {
+    MEMO_TEMPLATE.expand(&Variables::from([("id".to_string(), id.to_string())]))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Memo {
+    pub id: u64,
+    pub title: String,
+    pub body: String,
+    /// Session that created the memo
+    pub author_session: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A memo without its body, as returned by searches.
+#[derive(Debug, Serialize)]
+pub struct MemoSummary {
+    pub id: u64,
+    pub uri: String,
+    pub title: String,
+    pub author_session: String,
+    pub updated_at: DateTime<Utc>,
+    /// First line of the body containing the query, if the match wasn't only in the title
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+}
+
+/// What the memo file holds.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Memos {
+    /// Ids are never reused, so a stale `memo://` URI can't name a different memo
+    next_id: u64,
+    memos: BTreeMap<u64, Memo>,
+}
+
+/// Server-wide memo store. Clones share the same memos.
+///
+/// Like [`CounterStore`](super::store::CounterStore), a store backed by a
+/// file writes every mutation through before it takes effect, and a failed
+/// write leaves the memos as they were.
+#[derive(Clone, Debug)]
+pub struct MemoStore {
+    memos: Arc<Mutex<Memos>>,
+    file: Option<Arc<PathBuf>>,
+}
+
+impl Default for MemoStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoStore {
+    pub fn new() -> Self {
+        Self::from_memos(Memos::default(), None)
+    }
+
+    /// Open a store persisted at `path`, loading any memos saved by a previous run.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let memos = match persist::load::<Memos>(path)? {
+            Some(memos) => {
+                info!(path = %path.display(), count = memos.memos.len(), "Loaded memos");
+                memos
+            }
+            None => {
+                info!(path = %path.display(), "No existing memo file, starting fresh");
+                Memos::default()
+            }
+        };
+        Ok(Self::from_memos(memos, Some(path.to_path_buf())))
+    }
+
+    fn from_memos(memos: Memos, file: Option<PathBuf>) -> Self {
+        Self {
+            memos: Arc::new(Mutex::new(memos)),
+            file: file.map(Arc::new),
+        }
+    }
+
+    /// Persist `staged`, then make it the live set.
+    ///
+    /// Nothing changes in memory if the write fails, so other sessions never
+    /// see a memo the client was told wasn't saved, nor is its id used up.
+    async fn commit(&self, memos: &mut Memos, staged: Memos) -> Result<(), McpError> {
+        persist::commit(self.file.clone(), memos, staged)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(
+                    "failed to persist memos",
+                    Some(json!({ "reason": e.to_string() })),
+                )
+            })
+    }
+
+    pub async fn create(&self, title: &str, body: &str, author: &str) -> Result<Memo, McpError> {
+        validate_title(title)?;
+        let mut memos = self.memos.lock().await;
+        let mut staged = memos.clone();
+        staged.next_id += 1;
+        let now = Utc::now();
+        let memo = Memo {
+            id: staged.next_id,
+            title: title.to_string(),
+            body: body.to_string(),
+            author_session: author.to_string(),
+            created_at: now,
+            updated_at: now,
+        };
+        staged.memos.insert(memo.id, memo.clone());
+        self.commit(&mut memos, staged).await?;
+        Ok(memo)
+    }
+
+    /// Apply `f` to the memo and bump its update time.
+    ///
+    /// If `f` fails the memo is left untouched.
+    async fn update(
+        &self,
+        id: u64,
+        f: impl FnOnce(&mut Memo) -> Result<(), McpError>,
+    ) -> Result<Memo, McpError> {
+        let mut memos = self.memos.lock().await;
+        let mut updated = memos.memos.get(&id).ok_or_else(|| not_found(id))?.clone();
+        f(&mut updated)?;
+        updated.updated_at = Utc::now();
+        let mut staged = memos.clone();
+        staged.memos.insert(id, updated.clone());
+        self.commit(&mut memos, staged).await?;
+        Ok(updated)
+    }
+
+    /// Add `text` to the end of the body, on a new line if the body doesn't end with one.
+    pub async fn append(&self, id: u64, text: &str) -> Result<Memo, McpError> {
+        self.update(id, |memo| {
+            if !memo.body.is_empty() && !memo.body.ends_with('\n') {
+                memo.body.push('\n');
+            }
+            memo.body.push_str(text);
+            Ok(())
+        })
+        .await
+    }
+
+    /// Replace the title, the body, or both.
+    pub async fn replace(
+        &self,
+        id: u64,
+        title: Option<String>,
+        body: Option<String>,
+    ) -> Result<Memo, McpError> {
+        if title.is_none() && body.is_none() {
+            return Err(McpError::invalid_params(
+                "give a new title, body or both",
+                Some(json!({ "id": id })),
+            ));
+        }
+        self.update(id, |memo| {
+            if let Some(title) = title {
+                validate_title(&title)?;
+                memo.title = title;
+            }
+            if let Some(body) = body {
+                memo.body = body;
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    pub async fn delete(&self, id: u64) -> Result<Memo, McpError> {
+        let mut memos = self.memos.lock().await;
+        let mut staged = memos.clone();
+        let memo = staged.memos.remove(&id).ok_or_else(|| not_found(id))?;
+        self.commit(&mut memos, staged).await?;
+        Ok(memo)
+    }
+
+    pub async fn get(&self, id: u64) -> Option<Memo> {
+        self.memos.lock().await.memos.get(&id).cloned()
+    }
+
+    /// Every memo, oldest first.
+    pub async fn list(&self) -> Vec<Memo> {
+        self.memos.lock().await.memos.values().cloned().collect()
+    }
+
+    /// Memos whose title or body contains `query`, ignoring case; an empty query matches all.
+    pub async fn search(&self, query: &str) -> Vec<MemoSummary> {
+        let query = query.to_lowercase();
+        let memos = self.memos.lock().await;
+        memos
+            .memos
+            .values()
+            .filter_map(|memo| {
+                let excerpt = memo
+                    .body
+                    .lines()
+                    .find(|line| !query.is_empty() && line.to_lowercase().contains(&query))
+                    .map(str::to_string);
+                let matched = excerpt.is_some() || memo.title.to_lowercase().contains(&query);
+                matched.then(|| MemoSummary {
+                    id: memo.id,
+                    uri: memo_uri(memo.id),
+                    title: memo.title.clone(),
+                    author_session: memo.author_session.clone(),
+                    updated_at: memo.updated_at,
+                    excerpt,
+                })
+            })
+            .collect()
+    }
+}
+
+fn not_found(id: u64) -> McpError {
+    McpError::invalid_params("memo not found", Some(json!({ "id": id })))
+}
+
+fn validate_title(title: &str) -> Result<(), McpError> {
+    if title.trim().is_empty() {
+        return Err(McpError::invalid_params(
+            "memo titles must not be empty",
+            Some(json!({ "title": title })),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[tokio::test]
+    async fn ids_are_not_reused_after_delete() {
+        let memos = MemoStore::new();
+        let first = memos.create("first", "", "s").await.unwrap();
+        let second = memos.create("second", "", "s").await.unwrap();
+        memos.delete(second.id).await.unwrap();
+        let third = memos.create("third", "", "s").await.unwrap();
+        assert_eq!((first.id, second.id, third.id), (1, 2, 3));
+        assert!(memos.get(second.id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn ids_survive_a_reload() {
+        let dir = TempDir::new("memos-reload");
+        let path = dir.join("memos.json");
+        let memos = MemoStore::load(&path).unwrap();
+        let memo = memos.create("gone", "", "s").await.unwrap();
+        memos.delete(memo.id).await.unwrap();
+
+        let reloaded = MemoStore::load(&path).unwrap();
+        assert!(reloaded.list().await.is_empty());
+        assert_eq!(reloaded.create("new", "", "s").await.unwrap().id, 2);
+    }
+
+    #[tokio::test]
+    async fn failed_write_changes_nothing() {
+        let dir = TempDir::new("memos-failed-write");
+        std::fs::create_dir(dir.join("state")).unwrap();
+        let memos = MemoStore::load(&dir.join("state/memos.json")).unwrap();
+        let kept = memos.create("kept", "body", "s").await.unwrap();
+
+        std::fs::remove_dir_all(dir.join("state")).unwrap();
+        assert!(memos.create("lost", "", "s").await.is_err());
+        assert!(memos.append(kept.id, "more").await.is_err());
+        assert!(memos.delete(kept.id).await.is_err());
+
+        let listed = memos.list().await;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].body, "body");
+        assert_eq!(listed[0].updated_at, kept.updated_at);
+
+        // The failed create didn't use up an id
+        std::fs::create_dir(dir.join("state")).unwrap();
+        assert_eq!(memos.create("next", "", "s").await.unwrap().id, 2);
+    }
+
+    #[tokio::test]
+    async fn search_excerpts_the_matching_line() {
+        let memos = MemoStore::new();
+        memos
+            .create("Groceries", "eggs\nOat Milk\nbread", "s")
+            .await
+            .unwrap();
+        memos.create("Milk run", "tuesday", "s").await.unwrap();
+        memos.create("Other", "nothing here", "s").await.unwrap();
+
+        let found = memos.search("milk").await;
+        let found: Vec<_> = found
+            .iter()
+            .map(|memo| (memo.title.as_str(), memo.excerpt.as_deref()))
+            .collect();
+        assert_eq!(found, [("Groceries", Some("Oat Milk")), ("Milk run", None)]);
+        assert_eq!(memos.search("").await.len(), 3);
+        assert!(memos.search("absent").await.is_empty());
+    }
+}
@@ -1 +1,11 @@
+pub mod catalog;
 pub mod counter;
+pub mod files;
+pub mod memos;
+pub mod notify;
+pub mod overflow;
+pub mod pagination;
+pub mod persist;
+pub mod store;
+pub mod subscriptions;
+pub mod template;
@@ -0,0 +1,39 @@
+//! Forwarding server-wide changes to one session as notifications.
+
+use rmcp::model::ServerNotification;
+use rmcp::{Peer, RoleServer};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+/// Send `peer` the notifications `pick` chooses for each change on `changes`,
+/// until `ct` is cancelled or the channel closes.
+///
+/// `pick` gets `None` when this receiver fell behind and changes were lost.
+pub async fn forward<T: Clone>(
+    mut changes: broadcast::Receiver<T>,
+    peer: Peer<RoleServer>,
+    ct: CancellationToken,
+    mut pick: impl FnMut(Option<T>) -> Vec<ServerNotification>,
+) {
+    loop {
+        let changed = tokio::select! {
+            _ = ct.cancelled() => return,
+            changed = changes.recv() => match changed {
+                Ok(changed) => Some(changed),
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "Notifications fell behind, some changes were lost");
+                    None
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        };
+        for notification in pick(changed) {
+            // rmcp reports an error for notifications it did deliver, so failures can't
+            // tell us the peer is gone; the session dropping the guard stops the loop
+            if let Err(e) = peer.send_notification(notification).await {
+                debug!("Notification not confirmed: {:?}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use clap::ValueEnum;
+use rmcp::Error as McpError;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+/// What to do when counter arithmetic leaves the `i64` range.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowPolicy {
+    /// Reject the operation and leave the value unchanged
+    #[default]
+    Error,
+    /// Clamp the result to `i64::MIN` / `i64::MAX`
+    Saturate,
+    /// Wrap around using two's complement arithmetic
+    Wrap,
+}
+
+impl OverflowPolicy {
+    pub fn add(self, lhs: i64, rhs: i64) -> Result<i64, McpError> {
+        match self {
+            Self::Error => lhs.checked_add(rhs).ok_or_else(|| overflow(lhs, rhs)),
+            Self::Saturate => Ok(lhs.saturating_add(rhs)),
+            Self::Wrap => Ok(lhs.wrapping_add(rhs)),
+        }
+    }
+}
+
+fn overflow(lhs: i64, rhs: i64) -> McpError {
+    McpError::invalid_params(
+        "integer overflow",
+        Some(json!({
+            "operation": "add",
+            "lhs": lhs,
+            "rhs": rhs,
+            "min": i64::MIN,
+            "max": i64::MAX,
+        })),
+    )
+}
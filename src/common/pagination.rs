@@ -0,0 +1,234 @@
+//! Cursor pagination for the `*/list` requests.
+//!
+//! Every list is ordered by a unique key (a URI or a name), and a cursor
+//! names the last key of the page it came from. The next page starts after
+//! that key, so items added or removed between requests never shift the
+//! pages: nothing is repeated or skipped, and items inserted before the
+//! cursor simply aren't seen by that walk.
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as BASE64;
+use rmcp::Error as McpError;
+use rmcp::model::PaginatedRequestParam;
+use serde_json::json;
+
+/// Items per page unless configured otherwise.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// One page of a list and the cursor for the next one, if there is more.
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Cuts lists into pages of a fixed size.
+#[derive(Debug, Clone, Copy)]
+pub struct Paginator {
+    page_size: usize,
+}
+
+impl Default for Paginator {
+    fn default() -> Self {
+        Self::new(DEFAULT_PAGE_SIZE)
+    }
+}
+
+impl Paginator {
+    pub fn new(page_size: usize) -> Self {
+        Self {
+            page_size: page_size.max(1),
+        }
+    }
+
+    /// The page of `items` that `request` asks for, ordered by `key`.
+    ///
+    /// `list` names the kind of list so a cursor from one can't be replayed
+    /// against another; keys must be unique within a list.
+    pub fn page<T>(
+        &self,
+        list: &str,
+        mut items: Vec<T>,
+        key: impl Fn(&T) -> &str,
+        request: Option<PaginatedRequestParam>,
+    ) -> Result<Page<T>, McpError> {
+        let after = match request.and_then(|request| request.cursor) {
+            Some(cursor) => Some(decode(list, &cursor)?),
+            None => None,
+        };
+        items.sort_by(|a, b| key(a).cmp(key(b)));
+        let start = match &after {
+            Some(after) => items.partition_point(|item| key(item) <= after.as_str()),
+            None => 0,
+        };
+        let mut items: Vec<T> = items.into_iter().skip(start).collect();
+        let next_cursor = (items.len() > self.page_size).then(|| {
+            items.truncate(self.page_size);
+            encode(list, key(items.last().expect("page is not empty")))
+        });
+        Ok(Page { items, next_cursor })
+    }
+}
+
+fn encode(list: &str, key: &str) -> String {
+    BASE64.encode(format!("{list}:{key}"))
+}
+
+fn decode(list: &str, cursor: &str) -> Result<String, McpError> {
+    BASE64
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|decoded| Some(decoded.strip_prefix(list)?.strip_prefix(':')?.to_string()))
+        .ok_or_else(|| {
+            McpError::invalid_params("invalid cursor", Some(json!({ "cursor": cursor })))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use rmcp::model::ErrorCode;
+
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    fn page(
+        paginator: Paginator,
+        list: &str,
+        items: Vec<String>,
+        cursor: Option<String>,
+    ) -> Result<Page<String>, McpError> {
+        paginator.page(
+            list,
+            items,
+            String::as_str,
+            Some(PaginatedRequestParam { cursor }),
+        )
+    }
+
+    fn assert_invalid_cursor(result: Result<Page<String>, McpError>) {
+        let error = result.expect_err("cursor should be rejected");
+        assert_eq!(error.code, ErrorCode::INVALID_PARAMS);
+        assert_eq!(error.message, "invalid cursor");
+    }
+
+    #[test]
+    fn walks_every_page_in_order() {
+        let paginator = Paginator::new(2);
+        let items = names(&["e", "c", "a", "d", "b"]);
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        let mut pages = 0;
+        loop {
+            let page = page(paginator, "tools", items.clone(), cursor).unwrap();
+            assert!(page.items.len() <= 2);
+            seen.extend(page.items);
+            pages += 1;
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(pages, 3);
+        assert_eq!(seen, names(&["a", "b", "c", "d", "e"]));
+    }
+
+    #[test]
+    fn no_request_starts_at_the_beginning() {
+        let page = Paginator::new(2)
+            .page("tools", names(&["b", "a", "c"]), String::as_str, None)
+            .unwrap();
+        assert_eq!(page.items, names(&["a", "b"]));
+        assert!(page.next_cursor.is_some());
+    }
+
+    #[test]
+    fn inserts_between_pages_neither_repeat_nor_skip() {
+        let paginator = Paginator::new(2);
+        let first = page(paginator, "tools", names(&["b", "d", "f", "h"]), None).unwrap();
+        assert_eq!(first.items, names(&["b", "d"]));
+
+        // "a" and "c" land before the cursor, "e" and "g" after it
+        let items = names(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+        let second = page(paginator, "tools", items.clone(), first.next_cursor).unwrap();
+        assert_eq!(second.items, names(&["e", "f"]));
+        let third = page(paginator, "tools", items, second.next_cursor).unwrap();
+        assert_eq!(third.items, names(&["g", "h"]));
+        assert!(third.next_cursor.is_none());
+    }
+
+    #[test]
+    fn removing_the_cursor_item_does_not_skip() {
+        let paginator = Paginator::new(2);
+        let first = page(paginator, "tools", names(&["a", "b", "c", "d"]), None).unwrap();
+        let second = page(
+            paginator,
+            "tools",
+            names(&["a", "c", "d"]),
+            first.next_cursor,
+        )
+        .unwrap();
+        assert_eq!(second.items, names(&["c", "d"]));
+    }
+
+    #[test]
+    fn rejects_a_cursor_from_another_list() {
+        let paginator = Paginator::new(1);
+        let tools = page(paginator, "tools", names(&["a", "b"]), None).unwrap();
+        assert_invalid_cursor(page(
+            paginator,
+            "prompts",
+            names(&["a", "b"]),
+            tools.next_cursor,
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let paginator = Paginator::new(1);
+        let cursors = [
+            "not base64!".to_string(),
+            BASE64.encode([0xff, 0xfe, b':', b'a']),
+            BASE64.encode("tools"),
+            BASE64.encode("toolsa"),
+            String::new(),
+        ];
+        for cursor in cursors {
+            assert_invalid_cursor(page(paginator, "tools", names(&["a"]), Some(cursor)));
+        }
+    }
+
+    #[test]
+    fn page_size_boundaries() {
+        let paginator = Paginator::new(3);
+        let exact = page(paginator, "tools", names(&["a", "b", "c"]), None).unwrap();
+        assert_eq!(exact.items.len(), 3);
+        assert!(exact.next_cursor.is_none());
+
+        let over = page(paginator, "tools", names(&["a", "b", "c", "d"]), None).unwrap();
+        assert_eq!(over.items.len(), 3);
+        let last = page(
+            paginator,
+            "tools",
+            names(&["a", "b", "c", "d"]),
+            over.next_cursor,
+        )
+        .unwrap();
+        assert_eq!(last.items, names(&["d"]));
+        assert!(last.next_cursor.is_none());
+
+        let empty = page(paginator, "tools", Vec::new(), None).unwrap();
+        assert!(empty.items.is_empty());
+        assert!(empty.next_cursor.is_none());
+    }
+
+    #[test]
+    fn page_size_is_at_least_one() {
+        let page = page(Paginator::new(0), "tools", names(&["a", "b"]), None).unwrap();
+        assert_eq!(page.items, names(&["a"]));
+        assert!(page.next_cursor.is_some());
+    }
+}
@@ -0,0 +1,87 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use tracing::debug;
+
+/// Read JSON state from `path`, returning `None` if the file does not exist yet.
+pub fn load<T: DeserializeOwned>(path: &Path) -> io::Result<Option<T>> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    serde_json::from_slice(&bytes)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Atomically replace `path` with `state` as JSON.
+///
+/// The data is written to a sibling temporary file, synced, and renamed over
+/// the target so a crash never leaves a truncated state file behind.
+pub fn save<T: Serialize>(path: &Path, state: &T) -> io::Result<()> {
+    let tmp = tmp_path(path);
+    let bytes = serde_json::to_vec_pretty(state)?;
+    {
+        let mut file = File::create(&tmp)?;
+        file.write_all(&bytes)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp, path)?;
+    sync_parent_dir(path)
+}
+
+/// [`save`] on a blocking task, handing `state` back once it is on disk.
+pub async fn save_blocking<T>(path: Arc<PathBuf>, state: T) -> io::Result<T>
+where
+    T: Serialize + Send + 'static,
+{
+    let state = tokio::task::spawn_blocking({
+        let path = path.clone();
+        move || save(&path, &state).map(|()| state)
+    })
+    .await
+    .map_err(io::Error::other)??;
+    debug!(path = %path.display(), "Flushed state");
+    Ok(state)
+}
+
+/// Write `staged` to `path`, if there is one, and only then make it `live`.
+///
+/// A failed write leaves `live` as it was. Callers hold the lock guarding
+/// `live` so writes land in mutation order.
+pub async fn commit<T>(path: Option<Arc<PathBuf>>, live: &mut T, staged: T) -> io::Result<()>
+where
+    T: Serialize + Send + 'static,
+{
+    *live = match path {
+        Some(path) => save_blocking(path, staged).await?,
+        None => staged,
+    };
+    Ok(())
+}
+
+fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> io::Result<()> {
+    // Make the rename itself durable
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => File::open(dir)?.sync_all(),
+        _ => File::open(".")?.sync_all(),
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use rmcp::Error as McpError;
+use serde_json::json;
+use tokio::sync::{Mutex, broadcast};
+use tracing::info;
+
+use super::overflow::OverflowPolicy;
+use super::persist;
+
+/// Name of the counter used when a tool call does not specify one.
+pub const DEFAULT_COUNTER: &str = "default";
+
+const CHANGE_CHANNEL_CAPACITY: usize = 256;
+
+/// Registry of named counters. Clones share the same underlying map.
+///
+/// When backed by a state file, every mutation is written through to disk
+/// before it takes effect, and a failed write leaves the counters as they
+/// were. The name of every changed counter is published on a broadcast
+/// channel for resource subscribers.
+#[derive(Clone, Debug)]
+pub struct CounterStore {
+    counters: Arc<Mutex<BTreeMap<String, i64>>>,
+    state_file: Option<Arc<PathBuf>>,
+    overflow: OverflowPolicy,
+    changes: broadcast::Sender<String>,
+}
+
+impl CounterStore {
+    pub fn new() -> Self {
+        Self::from_counters(BTreeMap::new(), None)
+    }
+
+    /// Open a store persisted at `path`, loading any values saved by a previous run.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let counters = match persist::load::<BTreeMap<String, i64>>(path)? {
+            Some(counters) => {
+                info!(path = %path.display(), count = counters.len(), "Loaded counter state");
+                counters
+            }
+            None => {
+                info!(path = %path.display(), "No existing state file, starting fresh");
+                BTreeMap::new()
+            }
+        };
+        Ok(Self::from_counters(counters, Some(path.to_path_buf())))
+    }
+
+    fn from_counters(mut counters: BTreeMap<String, i64>, state_file: Option<PathBuf>) -> Self {
+        counters.entry(DEFAULT_COUNTER.to_string()).or_insert(0);
+        let (changes, _) = broadcast::channel(CHANGE_CHANNEL_CAPACITY);
+        Self {
+            counters: Arc::new(Mutex::new(counters)),
+            state_file: state_file.map(Arc::new),
+            overflow: OverflowPolicy::default(),
+            changes,
+        }
+    }
+
+    pub fn with_overflow(mut self, overflow: OverflowPolicy) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    pub fn overflow(&self) -> OverflowPolicy {
+        self.overflow
+    }
+
+    /// Write the current values to the state file, if there is one.
+    pub async fn flush(&self) -> io::Result<()> {
+        let Some(path) = self.state_file.clone() else {
+            return Ok(());
+        };
+        let counters = self.counters.lock().await;
+        persist::save_blocking(path, counters.clone()).await?;
+        Ok(())
+    }
+
+    /// Receive the name of each counter as it is modified, created or deleted.
+    pub fn changes(&self) -> broadcast::Receiver<String> {
+        self.changes.subscribe()
+    }
+
+    fn notify(&self, name: &str) {
+        // No receivers just means nobody is subscribed
+        let _ = self.changes.send(name.to_string());
+    }
+
+    /// Persist `staged`, then make it the live map and announce `name` as changed.
+    ///
+    /// Nothing changes in memory if the write fails, so other sessions never
+    /// see a value the client was told didn't stick.
+    async fn commit(
+        &self,
+        counters: &mut BTreeMap<String, i64>,
+        staged: BTreeMap<String, i64>,
+        name: &str,
+    ) -> Result<(), McpError> {
+        persist::commit(self.state_file.clone(), counters, staged)
+            .await
+            .map_err(|e| {
+                McpError::internal_error(
+                    "failed to persist counter state",
+                    Some(json!({ "reason": e.to_string() })),
+                )
+            })?;
+        self.notify(name);
+        Ok(())
+    }
+
+    /// Apply `f` to the named counter and return its new value.
+    ///
+    /// If `f` fails the counter is left untouched.
+    pub async fn update(
+        &self,
+        name: &str,
+        f: impl FnOnce(i64) -> Result<i64, McpError>,
+    ) -> Result<i64, McpError> {
+        let mut counters = self.counters.lock().await;
+        let value = *counters.get(name).ok_or_else(|| not_found(name))?;
+        let value = f(value)?;
+        let mut staged = counters.clone();
+        staged.insert(name.to_string(), value);
+        self.commit(&mut counters, staged, name).await?;
+        Ok(value)
+    }
+
+    /// Add `delta` to the named counter according to the store's overflow policy.
+    pub async fn add(&self, name: &str, delta: i64) -> Result<i64, McpError> {
+        let overflow = self.overflow;
+        self.update(name, |v| overflow.add(v, delta)).await
+    }
+
+    pub async fn set(&self, name: &str, value: i64) -> Result<i64, McpError> {
+        self.update(name, |_| Ok(value)).await
+    }
+
+    /// Set the named counter to `new` only if it currently holds `expected`.
+    ///
+    /// Returns whether the swap happened along with the counter's value afterwards.
+    pub async fn compare_and_swap(
+        &self,
+        name: &str,
+        expected: i64,
+        new: i64,
+    ) -> Result<(bool, i64), McpError> {
+        let mut counters = self.counters.lock().await;
+        let value = *counters.get(name).ok_or_else(|| not_found(name))?;
+        if value != expected {
+            return Ok((false, value));
+        }
+        let mut staged = counters.clone();
+        staged.insert(name.to_string(), new);
+        self.commit(&mut counters, staged, name).await?;
+        Ok((true, new))
+    }
+
+    pub async fn get(&self, name: &str) -> Result<i64, McpError> {
+        let counters = self.counters.lock().await;
+        counters.get(name).copied().ok_or_else(|| not_found(name))
+    }
+
+    pub async fn create(&self, name: &str) -> Result<(), McpError> {
+        validate_name(name)?;
+        let mut counters = self.counters.lock().await;
+        if counters.contains_key(name) {
+            return Err(McpError::invalid_params(
+                "counter already exists",
+                Some(json!({ "name": name })),
+            ));
+        }
+        let mut staged = counters.clone();
+        staged.insert(name.to_string(), 0);
+        self.commit(&mut counters, staged, name).await
+    }
+
+    pub async fn delete(&self, name: &str) -> Result<i64, McpError> {
+        if name == DEFAULT_COUNTER {
+            return Err(McpError::invalid_params(
+                "the default counter cannot be deleted",
+                Some(json!({ "name": name })),
+            ));
+        }
+        let mut counters = self.counters.lock().await;
+        let mut staged = counters.clone();
+        let value = staged.remove(name).ok_or_else(|| not_found(name))?;
+        self.commit(&mut counters, staged, name).await?;
+        Ok(value)
+    }
+
+    /// Snapshot of every counter, ordered by name.
+    pub async fn list(&self) -> BTreeMap<String, i64> {
+        self.counters.lock().await.clone()
+    }
+}
+
+fn not_found(name: &str) -> McpError {
+    McpError::invalid_params("counter not found", Some(json!({ "name": name })))
+}
+
+fn validate_name(name: &str) -> Result<(), McpError> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(McpError::invalid_params(
+            "counter names must be non-empty and use only letters, digits, '_', '-' or '.'",
+            Some(json!({ "name": name })),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The default counter set to `value` under `overflow`.
+    async fn at(value: i64, overflow: OverflowPolicy) -> CounterStore {
+        let store = CounterStore::new().with_overflow(overflow);
+        store.set(DEFAULT_COUNTER, value).await.unwrap();
+        store
+    }
+
+    #[tokio::test]
+    async fn error_policy_rejects_overflow_and_keeps_the_value() {
+        for (value, delta) in [(i64::MAX, 1), (i64::MIN, -1)] {
+            let store = at(value, OverflowPolicy::Error).await;
+            let error = store.add(DEFAULT_COUNTER, delta).await.unwrap_err();
+            assert_eq!(error.message, "integer overflow");
+            assert_eq!(store.get(DEFAULT_COUNTER).await.unwrap(), value);
+        }
+    }
+
+    #[tokio::test]
+    async fn saturate_policy_clamps_at_the_bounds() {
+        let store = at(i64::MAX - 1, OverflowPolicy::Saturate).await;
+        assert_eq!(store.add(DEFAULT_COUNTER, 5).await.unwrap(), i64::MAX);
+        assert_eq!(store.add(DEFAULT_COUNTER, 1).await.unwrap(), i64::MAX);
+
+        let store = at(i64::MIN + 1, OverflowPolicy::Saturate).await;
+        assert_eq!(store.add(DEFAULT_COUNTER, -5).await.unwrap(), i64::MIN);
+        assert_eq!(store.add(DEFAULT_COUNTER, -1).await.unwrap(), i64::MIN);
+    }
+
+    #[tokio::test]
+    async fn wrap_policy_wraps_around() {
+        let store = at(i64::MAX, OverflowPolicy::Wrap).await;
+        assert_eq!(store.add(DEFAULT_COUNTER, 1).await.unwrap(), i64::MIN);
+
+        let store = at(i64::MIN, OverflowPolicy::Wrap).await;
+        assert_eq!(store.add(DEFAULT_COUNTER, -2).await.unwrap(), i64::MAX - 1);
+    }
+
+    #[tokio::test]
+    async fn compare_and_swap_with_a_stale_value_changes_nothing() {
+        let store = at(7, OverflowPolicy::Error).await;
+        let mut changes = store.changes();
+        assert_eq!(
+            store
+                .compare_and_swap(DEFAULT_COUNTER, 6, 100)
+                .await
+                .unwrap(),
+            (false, 7)
+        );
+        assert_eq!(store.get(DEFAULT_COUNTER).await.unwrap(), 7);
+        assert!(changes.try_recv().is_err());
+
+        assert_eq!(
+            store
+                .compare_and_swap(DEFAULT_COUNTER, 7, 100)
+                .await
+                .unwrap(),
+            (true, 100)
+        );
+        assert_eq!(store.get(DEFAULT_COUNTER).await.unwrap(), 100);
+    }
+}
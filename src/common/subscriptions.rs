@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use rmcp::model::{
+    ResourceUpdatedNotification, ResourceUpdatedNotificationParam, ServerNotification,
+};
+use rmcp::{Peer, RoleServer};
+use tokio::sync::broadcast;
+use tokio_util::sync::{CancellationToken, DropGuard};
+use tracing::debug;
+
+use super::counter::counter_uri;
+use super::notify;
+
+/// Resource URIs a single session has subscribed to.
+///
+/// The first subscription spawns a task that listens for store changes and
+/// forwards matching ones to the session's peer as
+/// `notifications/resources/updated`. The task runs until every clone is
+/// dropped, i.e. until the session's service goes away.
+#[derive(Clone, Default)]
+pub struct Subscriptions {
+    uris: Arc<Mutex<HashSet<String>>>,
+    /// Held only here, never by the task, so dropping the last clone stops it
+    forwarder: Arc<Mutex<Option<DropGuard>>>,
+}
+
+impl Subscriptions {
+    pub async fn subscribe(
+        &self,
+        uri: String,
+        peer: Peer<RoleServer>,
+        changes: broadcast::Receiver<String>,
+    ) {
+        self.uris
+            .lock()
+            .expect("subscriptions lock poisoned")
+            .insert(uri);
+        let mut forwarder = self.forwarder.lock().expect("subscriptions lock poisoned");
+        if forwarder.is_none() {
+            let ct = CancellationToken::new();
+            let uris = self.uris.clone();
+            tokio::spawn(notify::forward(changes, peer, ct.clone(), move |changed| {
+                updated(&uris, changed)
+            }));
+            *forwarder = Some(ct.drop_guard());
+        }
+    }
+
+    pub async fn unsubscribe(&self, uri: &str) {
+        self.uris
+            .lock()
+            .expect("subscriptions lock poisoned")
+            .remove(uri);
+    }
+}
+
+/// `notifications/resources/updated` for the counter named by `changed` if
+/// `uris` holds it; after missed changes, for every URI in `uris`.
+fn updated(uris: &Mutex<HashSet<String>>, changed: Option<String>) -> Vec<ServerNotification> {
+    let uris = uris.lock().expect("subscriptions lock poisoned");
+    let updated: Vec<String> = match changed {
+        Some(name) => {
+            let uri = counter_uri(&name);
+            if uris.contains(&uri) {
+                vec![uri]
+            } else {
+                Vec::new()
+            }
+        }
+        None => uris.iter().cloned().collect(),
+    };
+    updated
+        .into_iter()
+        .map(|uri| {
+            debug!(%uri, "Sending resource updated notification");
+            ServerNotification::ResourceUpdatedNotification(ResourceUpdatedNotification {
+                method: Default::default(),
+                params: ResourceUpdatedNotificationParam { uri },
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,405 @@
+//! RFC 6570 URI templates, levels 1 and 2, and a router built on them.
+//!
+//! Supported expressions are `{var}` (simple string expansion), `{+var}`
+//! (reserved expansion) and `{#var}` (fragment expansion), one variable each.
+//! Templates are used both ways: expanding variables into a URI, and matching
+//! a requested URI to pull the variables back out.
+
+use std::collections::HashMap;
+use std::fmt;
+
+/// Characters a simple expansion passes through unencoded.
+fn is_unreserved(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~')
+}
+
+fn is_reserved(c: char) -> bool {
+    matches!(
+        c,
+        ':' | '/'
+            | '?'
+            | '#'
+            | '['
+            | ']'
+            | '@'
+            | '!'
+            | '$'
+            | '&'
+            | '\''
+            | '('
+            | ')'
+            | '*'
+            | '+'
+            | ','
+            | ';'
+            | '='
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    /// `{var}`
+    Simple,
+    /// `{+var}`
+    Reserved,
+    /// `{#var}`
+    Fragment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Expression { operator: Operator, name: String },
+}
+
+/// A parsed URI template such as `counter://{name}` or `file:///{+path}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UriTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+/// Variables pulled out of a matched URI, already percent-decoded.
+pub type Variables = HashMap<String, String>;
+
+impl UriTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while !rest.is_empty() {
+            let Some(start) = rest.find(['{', '}']) else {
+                parts.push(Part::Literal(rest.to_string()));
+                break;
+            };
+            if rest[start..].starts_with('}') {
+                return Err(format!("unmatched }} in URI template {template:?}"));
+            }
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("unclosed {{ in URI template {template:?}"))?;
+            let expression = &rest[start + 1..start + end];
+            let (operator, name) = match expression.chars().next() {
+                Some('+') => (Operator::Reserved, &expression[1..]),
+                Some('#') => (Operator::Fragment, &expression[1..]),
+                _ => (Operator::Simple, expression),
+            };
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.'));
+            if !valid {
+                return Err(format!(
+                    "unsupported expression {{{expression}}} in URI template {template:?}; only {{var}}, {{+var}} and {{#var}} are allowed"
+                ));
+            }
+            if let Some(Part::Expression { .. }) = parts.last() {
+                return Err(format!(
+                    "URI template {template:?} needs literal text between expressions"
+                ));
+            }
+            parts.push(Part::Expression {
+                operator,
+                name: name.to_string(),
+            });
+            rest = &rest[start + end + 1..];
+        }
+        Ok(Self {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    /// Build a URI; undefined variables expand to nothing, as RFC 6570 says.
+    pub fn expand(&self, variables: &Variables) -> String {
+        let mut uri = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => uri.push_str(literal),
+                Part::Expression { operator, name } => {
+                    let Some(value) = variables.get(name) else {
+                        continue;
+                    };
+                    if *operator == Operator::Fragment {
+                        uri.push('#');
+                    }
+                    encode(&mut uri, value, *operator != Operator::Simple);
+                }
+            }
+        }
+        uri
+    }
+
+    /// Pull the variables out of `uri`, or `None` if it doesn't fit the template.
+    ///
+    /// Every variable must be non-empty. A simple `{var}` stops at `/`, `?`
+    /// and `#`, so `counter://{name}` won't swallow a path; reserved and
+    /// fragment expressions take whatever the rest of the template leaves.
+    pub fn matches(&self, uri: &str) -> Option<Variables> {
+        let mut variables = Variables::new();
+        match_parts(&self.parts, uri, &mut variables).then_some(variables)
+    }
+}
+
+impl fmt::Display for UriTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+fn match_parts(parts: &[Part], uri: &str, variables: &mut Variables) -> bool {
+    let Some((part, rest)) = parts.split_first() else {
+        return uri.is_empty();
+    };
+    match part {
+        Part::Literal(literal) => uri
+            .strip_prefix(literal.as_str())
+            .is_some_and(|uri| match_parts(rest, uri, variables)),
+        Part::Expression { operator, name } => {
+            let uri = match operator {
+                Operator::Fragment => match uri.strip_prefix('#') {
+                    Some(uri) => uri,
+                    None => return false,
+                },
+                _ => uri,
+            };
+            let longest = match operator {
+                Operator::Simple => uri.find(['/', '?', '#']).unwrap_or(uri.len()),
+                _ => uri.len(),
+            };
+            // Prefer the longest value that still lets the rest match
+            for end in (1..=longest).rev().filter(|&end| uri.is_char_boundary(end)) {
+                let Some(value) = decode(&uri[..end]) else {
+                    continue;
+                };
+                if match_parts(rest, &uri[end..], variables) {
+                    variables.insert(name.clone(), value);
+                    return true;
+                }
+            }
+            false
+        }
+    }
+}
+
+/// Percent-encode `value`, keeping reserved characters as they are if `allow_reserved`.
+///
+/// `%` is always encoded, even where RFC 6570 would pass an existing `%XX`
+/// through, since matching decodes every `%XX` and a value like `a%41` would
+/// otherwise come back as `aA`.
+fn encode(uri: &mut String, value: &str, allow_reserved: bool) {
+    for c in value.chars() {
+        if is_unreserved(c) || allow_reserved && is_reserved(c) {
+            uri.push(c);
+        } else {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                uri.push_str(&format!("%{byte:02X}"));
+            }
+        }
+    }
+}
+
+/// Percent-decode; a `%` not followed by two hex digits is kept as is.
+fn decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+/// Dispatches URIs to whichever route's template they match first.
+#[derive(Debug, Clone)]
+pub struct Router<R> {
+    routes: Vec<(UriTemplate, R)>,
+}
+
+impl<R> Default for Router<R> {
+    fn default() -> Self {
+        Self { routes: Vec::new() }
+    }
+}
+
+impl<R> Router<R> {
+    pub fn route(mut self, template: UriTemplate, route: R) -> Self {
+        self.routes.push((template, route));
+        self
+    }
+
+    pub fn resolve(&self, uri: &str) -> Option<(&UriTemplate, &R, Variables)> {
+        self.routes.iter().find_map(|(template, route)| {
+            template
+                .matches(uri)
+                .map(|variables| (template, route, variables))
+        })
+    }
+
+    pub fn routes(&self) -> impl Iterator<Item = (&UriTemplate, &R)> {
+        self.routes
+            .iter()
+            .map(|(template, route)| (template, route))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(template: &str) -> UriTemplate {
+        UriTemplate::parse(template).expect("valid URI template")
+    }
+
+    fn variables(pairs: &[(&str, &str)]) -> Variables {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// Expand `pairs`, check the URI, and check matching it gives `pairs` back.
+    fn round_trip(template: &UriTemplate, pairs: &[(&str, &str)], uri: &str) {
+        let variables = variables(pairs);
+        assert_eq!(template.expand(&variables), uri);
+        assert_eq!(template.matches(uri), Some(variables));
+    }
+
+    #[test]
+    fn rejects_malformed_templates() {
+        for bad in [
+            "counter://{name",
+            "counter://name}",
+            "counter://{}",
+            "counter://{+}",
+            "counter://{?name}",
+            "counter://{a,b}",
+            "counter://{a}{b}",
+        ] {
+            assert!(UriTemplate::parse(bad).is_err(), "{bad:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn accepts_supported_expressions() {
+        for good in [
+            "counter://{name}",
+            "file:///{+path}",
+            "doc://x{#section}",
+            "plain://uri",
+        ] {
+            assert_eq!(template(good).to_string(), good);
+        }
+    }
+
+    #[test]
+    fn simple_expansion_round_trips() {
+        let counter = template("counter://{name}");
+        round_trip(&counter, &[("name", "default")], "counter://default");
+        round_trip(&counter, &[("name", "a b/c")], "counter://a%20b%2Fc");
+        round_trip(&counter, &[("name", "naïve")], "counter://na%C3%AFve");
+    }
+
+    #[test]
+    fn reserved_expansion_round_trips() {
+        let file = template("file:///{+path}");
+        round_trip(
+            &file,
+            &[("path", "docs/read me.md")],
+            "file:///docs/read%20me.md",
+        );
+        round_trip(&file, &[("path", "a/b?c=d#e")], "file:///a/b?c=d#e");
+    }
+
+    #[test]
+    fn percent_signs_round_trip() {
+        let file = template("file:///{+path}");
+        round_trip(&file, &[("path", "a%41.txt")], "file:///a%2541.txt");
+        round_trip(&file, &[("path", "100%")], "file:///100%25");
+        let counter = template("counter://{name}");
+        round_trip(&counter, &[("name", "%2F")], "counter://%252F");
+    }
+
+    #[test]
+    fn stray_percent_in_uri_is_kept() {
+        assert_eq!(
+            template("counter://{name}").matches("counter://50%"),
+            Some(variables(&[("name", "50%")]))
+        );
+        assert_eq!(
+            template("counter://{name}").matches("counter://5%zz"),
+            Some(variables(&[("name", "5%zz")]))
+        );
+    }
+
+    #[test]
+    fn simple_expression_stops_at_delimiters() {
+        let counter = template("counter://{name}");
+        assert_eq!(counter.matches("counter://a/b"), None);
+        assert_eq!(counter.matches("counter://a?b"), None);
+        assert_eq!(counter.matches("counter://a#b"), None);
+    }
+
+    #[test]
+    fn empty_variables_do_not_match() {
+        assert_eq!(template("counter://{name}").matches("counter://"), None);
+        assert_eq!(template("file:///{+path}").matches("file:///"), None);
+        assert_eq!(template("counter://{name}").matches("memo://1"), None);
+    }
+
+    #[test]
+    fn backtracks_to_the_longest_value_that_fits() {
+        assert_eq!(
+            template("file:///{+path}.txt").matches("file:///notes.txt.txt"),
+            Some(variables(&[("path", "notes.txt")]))
+        );
+        assert_eq!(
+            template("x://{+dir}/{name}").matches("x://a/b/c"),
+            Some(variables(&[("dir", "a/b"), ("name", "c")]))
+        );
+        assert_eq!(template("x://{+dir}/{name}").matches("x://a/"), None);
+    }
+
+    #[test]
+    fn fragment_expansion() {
+        let doc = template("doc://x{#section}");
+        round_trip(&doc, &[("section", "intro")], "doc://x#intro");
+        round_trip(&doc, &[("section", "a/b c")], "doc://x#a/b%20c");
+        // An undefined variable expands to nothing, `#` included
+        assert_eq!(doc.expand(&Variables::new()), "doc://x");
+        assert_eq!(doc.matches("doc://x"), None);
+        assert_eq!(doc.matches("doc://xintro"), None);
+    }
+
+    #[test]
+    fn router_resolves_first_matching_route() {
+        let router = Router::default()
+            .route(template("counter://{name}"), 1)
+            .route(template("counter://{+path}"), 2);
+        let (matched, route, variables) = router.resolve("counter://a").expect("route");
+        assert_eq!(
+            (matched.to_string().as_str(), *route),
+            ("counter://{name}", 1)
+        );
+        assert_eq!(variables, self::variables(&[("name", "a")]));
+        let (_, route, _) = router.resolve("counter://a/b").expect("route");
+        assert_eq!(*route, 2);
+        assert!(router.resolve("memo://1").is_none());
+    }
+}
@@ -0,0 +1,390 @@
+//! Layered server configuration.
+//!
+//! Each setting is taken from the first of these that has it: the command
+//! line, an `XP_MCP_*` environment variable, the `--config` TOML file, and
+//! finally the built-in default. The file uses the long flag names with
+//! underscores as keys, plus `[[resources]]` and `[[prompts]]` tables that
+//! have no command-line equivalent.
+
+use std::fmt::Display;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::{Deserialize, Serialize, Serializer};
+
+use crate::common::catalog::{self, Catalog, PromptDef, ResourceDef};
+use crate::common::files::{DEFAULT_FILE_MAX_SIZE, FileRoots};
+use crate::common::overflow::OverflowPolicy;
+use crate::common::pagination::DEFAULT_PAGE_SIZE;
+use crate::logging::{self, LogFormat, LogTarget};
+use crate::{Args, StateMode, TransportType};
+
+pub const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1:8000";
+pub const DEFAULT_WS_MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+pub const DEFAULT_WS_PING_INTERVAL: u64 = 30;
+pub const DEFAULT_SOCKET_PATH: &str = "xp-mcp.sock";
+pub const DEFAULT_SOCKET_MODE: u32 = 0o600;
+pub const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 30;
+pub const DEFAULT_HTTP_IDLE_TIMEOUT: u64 = 300;
+pub const DEFAULT_MEMO_FILE: &str = "xp-mcp-memos.json";
+
+/// Contents of a `--config` file; every key is optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+    transport: Option<Vec<TransportType>>,
+    bind_address: Option<String>,
+    http_bind_address: Option<String>,
+    ws_bind_address: Option<String>,
+    http_idle_timeout: Option<u64>,
+    ws_max_message_size: Option<usize>,
+    ws_ping_interval: Option<u64>,
+    socket_path: Option<PathBuf>,
+    socket_mode: Option<String>,
+    log_level: Option<String>,
+    log_format: Option<LogFormat>,
+    log_target: Option<String>,
+    auth_tokens_file: Option<PathBuf>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    state_mode: Option<StateMode>,
+    state_file: Option<PathBuf>,
+    memo_file: Option<PathBuf>,
+    overflow: Option<OverflowPolicy>,
+    shutdown_timeout: Option<u64>,
+    page_size: Option<usize>,
+    tools: Option<Vec<String>>,
+    file_roots: Option<Vec<PathBuf>>,
+    file_max_size: Option<u64>,
+    resources: Option<Vec<ResourceDef>>,
+    prompts: Option<Vec<PromptDef>>,
+}
+
+impl FileConfig {
+    /// Read and parse `path`. Relative paths inside it are resolved against
+    /// the file's directory, so the file works from any working directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        let mut file: Self = toml::from_str(&text)
+            .with_context(|| format!("invalid config file {}", path.display()))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        for path in [
+            &mut file.socket_path,
+            &mut file.auth_tokens_file,
+            &mut file.tls_cert,
+            &mut file.tls_key,
+            &mut file.state_file,
+            &mut file.memo_file,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(file.file_roots.iter_mut().flatten())
+        {
+            *path = base.join(&*path);
+        }
+        if let Some(target) = &mut file.log_target
+            && let Ok(LogTarget::File(log)) = LogTarget::parse(target)
+        {
+            *target = base.join(log).display().to_string();
+        }
+        Ok(file)
+    }
+}
+
+/// The effective configuration, after every layer has been applied and checked.
+#[derive(Debug, Clone, Serialize)]
+pub struct Config {
+    pub transport: Vec<TransportType>,
+    pub bind_address: SocketAddr,
+    pub http_bind_address: SocketAddr,
+    pub ws_bind_address: SocketAddr,
+    pub http_idle_timeout: u64,
+    pub ws_max_message_size: usize,
+    pub ws_ping_interval: u64,
+    pub socket_path: PathBuf,
+    #[serde(serialize_with = "octal")]
+    pub socket_mode: u32,
+    pub log_level: String,
+    pub log_format: LogFormat,
+    #[serde(serialize_with = "display")]
+    pub log_target: LogTarget,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_tokens_file: Option<PathBuf>,
+    /// Secret, so never printed
+    #[serde(skip)]
+    pub auth_tokens: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<PathBuf>,
+    pub state_mode: StateMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub state_file: Option<PathBuf>,
+    pub memo_file: PathBuf,
+    pub overflow: OverflowPolicy,
+    pub shutdown_timeout: u64,
+    pub page_size: usize,
+    #[serde(flatten)]
+    pub catalog: Catalog,
+}
+
+/// Passes the names of the settings only read at startup to `$apply`.
+macro_rules! restart_only {
+    ($apply:ident) => {
+        $apply!(
+            transport,
+            bind_address,
+            http_bind_address,
+            ws_bind_address,
+            http_idle_timeout,
+            ws_max_message_size,
+            ws_ping_interval,
+            socket_path,
+            socket_mode,
+            log_format,
+            log_target,
+            auth_tokens_file,
+            tls_cert,
+            tls_key,
+            state_mode,
+            state_file,
+            memo_file,
+            overflow,
+            shutdown_timeout,
+            page_size,
+        )
+    };
+}
+
+impl Config {
+    /// Merge the command line (which clap has already merged with the
+    /// environment) over the config file and the defaults.
+    pub fn resolve(args: Args) -> Result<Self> {
+        let file = match &args.config {
+            Some(path) => FileConfig::load(path)?,
+            None => FileConfig::default(),
+        };
+        let from_file = |key: &str, e: String| match &args.config {
+            Some(path) => anyhow!("invalid {key} in {}: {e}", path.display()),
+            None => anyhow!("invalid {key}: {e}"),
+        };
+
+        let mut transport = Vec::new();
+        for t in args
+            .transport
+            .or(file.transport)
+            .unwrap_or_else(|| vec![TransportType::Sse])
+        {
+            if !transport.contains(&t) {
+                transport.push(t);
+            }
+        }
+        if transport.is_empty() {
+            bail!("at least one transport must be enabled");
+        }
+
+        let bind_address = parse_bind_address(
+            args.bind_address
+                .or(file.bind_address)
+                .as_deref()
+                .unwrap_or(DEFAULT_BIND_ADDRESS),
+        )?;
+        let http_bind_address = match args.http_bind_address.or(file.http_bind_address) {
+            Some(addr) => parse_bind_address(&addr)?,
+            None => bind_address,
+        };
+        let ws_bind_address = match args.ws_bind_address.or(file.ws_bind_address) {
+            Some(addr) => parse_bind_address(&addr)?,
+            None => bind_address,
+        };
+        let listeners: Vec<SocketAddr> = [
+            (TransportType::Sse, bind_address),
+            (TransportType::Http, http_bind_address),
+            (TransportType::WebSocket, ws_bind_address),
+        ]
+        .into_iter()
+        .filter(|(t, _)| transport.contains(t))
+        .map(|(_, addr)| addr)
+        .collect();
+        if (1..listeners.len()).any(|i| listeners[..i].contains(&listeners[i])) {
+            bail!(
+                "sse, http and websocket transports each need their own address; set --http-bind-address or --ws-bind-address"
+            );
+        }
+
+        let http_idle_timeout = args
+            .http_idle_timeout
+            .or(file.http_idle_timeout)
+            .unwrap_or(DEFAULT_HTTP_IDLE_TIMEOUT);
+        if http_idle_timeout == 0 {
+            bail!("http_idle_timeout must be at least 1 second");
+        }
+
+        let ws_ping_interval = args
+            .ws_ping_interval
+            .or(file.ws_ping_interval)
+            .unwrap_or(DEFAULT_WS_PING_INTERVAL);
+        if ws_ping_interval == 0 {
+            bail!("ws_ping_interval must be at least 1 second");
+        }
+
+        let socket_mode = match (args.socket_mode, file.socket_mode) {
+            (Some(mode), _) => mode,
+            (None, Some(mode)) => {
+                parse_socket_mode(&mode).map_err(|e| from_file("socket_mode", e))?
+            }
+            (None, None) => DEFAULT_SOCKET_MODE,
+        };
+
+        let log_level = match (args.log_level, file.log_level) {
+            (Some(level), _) => level,
+            (None, Some(level)) => {
+                logging::parse_directives(&level).map_err(|e| from_file("log_level", e))?
+            }
+            (None, None) => logging::default_directives()?,
+        };
+        // stdout carries JSON-RPC frames in stdio mode, so keep logs off it by default
+        let log_target = match (args.log_target, file.log_target) {
+            (Some(target), _) => target,
+            (None, Some(target)) => {
+                LogTarget::parse(&target).map_err(|e| from_file("log_target", e))?
+            }
+            (None, None) if transport.contains(&TransportType::Stdio) => LogTarget::Stderr,
+            (None, None) => LogTarget::Stdout,
+        };
+        if matches!(log_target, LogTarget::Stdout) && transport.contains(&TransportType::Stdio) {
+            bail!("log_target stdout would mix logs into the stdio transport's JSON-RPC stream");
+        }
+
+        let tls_cert = args.tls_cert.or(file.tls_cert);
+        let tls_key = args.tls_key.or(file.tls_key);
+        if tls_cert.is_some() != tls_key.is_some() {
+            bail!("tls_cert and tls_key must be set together");
+        }
+
+        let state_mode = args
+            .state_mode
+            .or(file.state_mode)
+            .unwrap_or(StateMode::Shared);
+        let state_file = args.state_file.or(file.state_file);
+        if matches!(state_mode, StateMode::Session) && state_file.is_some() {
+            bail!("--state-file requires --state-mode shared");
+        }
+
+        let page_size = args
+            .page_size
+            .or(file.page_size)
+            .unwrap_or(DEFAULT_PAGE_SIZE);
+        if page_size == 0 {
+            bail!("page_size must be at least 1");
+        }
+
+        let files = FileRoots::new(
+            args.file_roots.or(file.file_roots).unwrap_or_default(),
+            args.file_max_size
+                .or(file.file_max_size)
+                .unwrap_or(DEFAULT_FILE_MAX_SIZE),
+        )
+        .map_err(anyhow::Error::msg)?;
+        let catalog = Catalog::new(
+            file.resources.unwrap_or_default(),
+            file.prompts.unwrap_or_else(catalog::default_prompts),
+            args.tools.or(file.tools),
+            files,
+        )
+        .map_err(anyhow::Error::msg)?;
+
+        Ok(Self {
+            transport,
+            bind_address,
+            http_bind_address,
+            ws_bind_address,
+            http_idle_timeout,
+            ws_max_message_size: args
+                .ws_max_message_size
+                .or(file.ws_max_message_size)
+                .unwrap_or(DEFAULT_WS_MAX_MESSAGE_SIZE),
+            ws_ping_interval,
+            socket_path: args
+                .socket_path
+                .or(file.socket_path)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_SOCKET_PATH)),
+            socket_mode,
+            log_level,
+            log_format: args.log_format.or(file.log_format).unwrap_or_default(),
+            log_target,
+            auth_tokens_file: args.auth_tokens_file.or(file.auth_tokens_file),
+            auth_tokens: args.auth_tokens,
+            tls_cert,
+            tls_key,
+            state_mode,
+            state_file,
+            memo_file: args
+                .memo_file
+                .or(file.memo_file)
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_MEMO_FILE)),
+            overflow: args.overflow.or(file.overflow).unwrap_or_default(),
+            shutdown_timeout: args
+                .shutdown_timeout
+                .or(file.shutdown_timeout)
+                .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT),
+            page_size,
+            catalog,
+        })
+    }
+
+    /// Settings that differ in `other` but are only read at startup.
+    pub fn restart_required(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        macro_rules! compare {
+            ($($field:ident),* $(,)?) => {
+                $(if self.$field != other.$field {
+                    changed.push(stringify!($field));
+                })*
+            };
+        }
+        restart_only!(compare);
+        changed
+    }
+
+    /// `reloaded` with the settings only read at startup put back to ours,
+    /// since those keep running until a restart whatever the file now says.
+    pub fn keep_restart_only(&self, mut reloaded: Config) -> Config {
+        macro_rules! keep {
+            ($($field:ident),* $(,)?) => {
+                $(reloaded.$field = self.$field.clone();)*
+            };
+        }
+        restart_only!(keep);
+        reloaded
+    }
+
+    /// The effective configuration as a TOML document that `--config` accepts.
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(self).context("failed to render configuration")
+    }
+}
+
+pub fn parse_socket_mode(mode: &str) -> Result<u32, String> {
+    match u32::from_str_radix(mode, 8) {
+        Ok(mode) if mode <= 0o777 => Ok(mode),
+        _ => Err(format!("{mode:?} is not an octal permission mode like 600")),
+    }
+}
+
+fn parse_bind_address(bind_address: &str) -> Result<SocketAddr> {
+    bind_address
+        .parse()
+        .with_context(|| format!("invalid bind address {bind_address:?}"))
+}
+
+fn octal<S: Serializer>(mode: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format!("{mode:o}"))
+}
+
+fn display<T: Display, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
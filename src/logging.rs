@@ -0,0 +1,152 @@
+use std::fs::OpenOptions;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::{Context, Result, anyhow};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::prelude::*;
+use tracing_subscriber::{EnvFilter, Registry, fmt, reload};
+
+/// Directives used when no log level is configured and `RUST_LOG` is unset.
+const DEFAULT_DIRECTIVES: &str = "info";
+
+/// Where log lines are written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogTarget {
+    Stdout,
+    Stderr,
+    /// Append to the file at this path
+    File(PathBuf),
+    /// Discard all log output
+    None,
+}
+
+impl LogTarget {
+    /// Parse `stdout`, `stderr`, `none`, or treat anything else as a file path.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "stdout" => Ok(Self::Stdout),
+            "stderr" => Ok(Self::Stderr),
+            "none" => Ok(Self::None),
+            "" => Err("log target must not be empty".to_string()),
+            path => Ok(Self::File(PathBuf::from(path))),
+        }
+    }
+}
+
+impl std::fmt::Display for LogTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Stdout => f.write_str("stdout"),
+            Self::Stderr => f.write_str("stderr"),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::None => f.write_str("none"),
+        }
+    }
+}
+
+/// How each log event is rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Single-line human readable output
+    #[default]
+    Full,
+    /// Shorter single-line output
+    Compact,
+    /// Multi-line output for local debugging
+    Pretty,
+    /// Newline-delimited JSON objects for log shippers
+    Json,
+}
+
+const LEVELS: [&str; 6] = ["off", "error", "warn", "info", "debug", "trace"];
+
+/// Validate a `RUST_LOG`-style directive string such as `info,rmcp=warn`
+/// given as `--log-level` or in the config file.
+///
+/// This also rejects anything `EnvFilter` would silently accept but almost
+/// certainly isn't meant: a bare word is only allowed if it is a level, as
+/// `EnvFilter` would otherwise treat a typo like `debgu` as a target name and
+/// log nothing useful. Use `target=level` to filter a specific module.
+pub fn parse_directives(s: &str) -> Result<String, String> {
+    for directive in s.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+        let bare = !directive.contains(['=', '[']);
+        if bare && !LEVELS.contains(&directive.to_ascii_lowercase().as_str()) {
+            return Err(format!(
+                "unknown log level {directive:?}, expected one of {} or target=level",
+                LEVELS.join(", ")
+            ));
+        }
+    }
+    build_filter(s).map(|_| s.to_string())
+}
+
+fn build_filter(s: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(s).map_err(|e| e.to_string())
+}
+
+/// Directives to use when none were configured: `RUST_LOG` if set, otherwise `info`.
+///
+/// `RUST_LOG` is often exported for other programs too, so it gets the full
+/// `EnvFilter` syntax, bare targets included. An invalid value is still
+/// reported as an error rather than ignored.
+pub fn default_directives() -> Result<String> {
+    match std::env::var(EnvFilter::DEFAULT_ENV) {
+        Ok(env) => build_filter(&env)
+            .map(|_| env.clone())
+            .map_err(|e| anyhow!("invalid {} value {env:?}: {e}", EnvFilter::DEFAULT_ENV)),
+        Err(_) => Ok(DEFAULT_DIRECTIVES.to_string()),
+    }
+}
+
+/// Changes the filter of the installed subscriber while the server runs.
+#[derive(Debug, Clone)]
+pub struct LogHandle(Option<reload::Handle<EnvFilter, Registry>>);
+
+impl LogHandle {
+    /// Replace the filter directives; invalid ones leave the current filter in place.
+    pub fn set_directives(&self, directives: &str) -> Result<()> {
+        let filter = build_filter(directives).map_err(anyhow::Error::msg)?;
+        if let Some(handle) = &self.0 {
+            handle.reload(filter)?;
+        }
+        Ok(())
+    }
+}
+
+/// Install the global tracing subscriber.
+pub fn init(directives: &str, format: LogFormat, target: &LogTarget) -> Result<LogHandle> {
+    let filter = build_filter(directives).map_err(anyhow::Error::msg)?;
+
+    let (writer, ansi) = match target {
+        LogTarget::Stdout => (BoxMakeWriter::new(std::io::stdout), true),
+        LogTarget::Stderr => (BoxMakeWriter::new(std::io::stderr), true),
+        LogTarget::File(path) => {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("failed to open log file {}", path.display()))?;
+            (BoxMakeWriter::new(Mutex::new(file)), false)
+        }
+        LogTarget::None => return Ok(LogHandle(None)),
+    };
+
+    let layer = fmt::layer().with_writer(writer).with_ansi(ansi);
+    let layer = match format {
+        LogFormat::Full => layer.boxed(),
+        LogFormat::Compact => layer.compact().boxed(),
+        LogFormat::Pretty => layer.pretty().boxed(),
+        LogFormat::Json => layer.json().with_ansi(false).boxed(),
+    };
+
+    let (filter, handle) = reload::Layer::new(filter);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(layer)
+        .init();
+    Ok(LogHandle(Some(handle)))
+}
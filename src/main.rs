@@ -1,114 +1,619 @@
-use anyhow::Result;
-use clap::{Parser, ValueEnum};
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use common::catalog::LiveCatalog;
 use common::counter::Counter;
-use rmcp::ServiceExt;
-use rmcp::transport::sse_server::SseServer;
+use common::memos::MemoStore;
+use common::overflow::OverflowPolicy;
+use common::store::CounterStore;
+use config::Config;
+use logging::{LogFormat, LogTarget};
+use metrics::Metrics;
+use reload::Reloader;
 use rmcp::transport::stdio;
-use std::net::SocketAddr;
-use tracing::{debug, error, info};
-use tracing_subscriber::{self};
+use rmcp::{RoleServer, Service, ServiceExt};
+use serde::{Deserialize, Serialize};
+use session::Session;
+use status::ServerStatus;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{debug, error, info, warn};
+use transport::auth::TokenAuth;
+use transport::sse::{SseServer, SseServerConfig};
+use transport::streamable_http::{StreamableHttpConfig, StreamableHttpServer};
+use transport::tls::TlsConfig;
+#[cfg(unix)]
+use transport::unix::{UnixSocketConfig, UnixSocketServer};
+use transport::websocket::{WebSocketConfig, WebSocketServer};
+mod client;
 mod common;
+mod config;
+mod logging;
+mod metrics;
+mod reload;
+mod repl;
+mod session;
+mod shutdown;
+mod status;
+#[cfg(test)]
+mod testing;
+mod transport;
 
-/// RMCP server with support for both stdio and SSE transport
-#[derive(Parser, Debug)]
+/// RMCP server with support for stdio, SSE, streamable HTTP, WebSocket and Unix socket transports
+///
+/// Settings come from the command line, then XP_MCP_* environment variables, then the --config file.
+#[derive(Parser, Debug, Clone)]
 #[command(version, about)]
 struct Args {
-    /// Transport method to use
-    #[arg(short, long, value_enum, default_value_t = TransportType::Sse)]
-    transport: TransportType,
+    #[command(subcommand)]
+    command: Option<Command>,
 
-    /// Bind address for SSE server (only used with sse transport)
-    #[arg(short, long, default_value = "127.0.0.1:8000")]
-    bind_address: String,
+    /// TOML file with defaults for any of these settings, plus resources and prompts
+    #[arg(short, long, global = true, env = "XP_MCP_CONFIG")]
+    config: Option<PathBuf>,
 
-    /// Log level (trace, debug, info, warn, error)
-    #[arg(short, long, default_value = "info")]
-    log_level: String,
+    /// Reload prompts, resources, tools and log level whenever the --config file changes (SIGHUP always does)
+    #[arg(long, requires = "config", env = "XP_MCP_WATCH_CONFIG")]
+    watch_config: bool,
+
+    /// Transport methods to serve; repeat the flag or separate with commas to run several at once [default: sse]
+    #[arg(
+        short,
+        long,
+        value_enum,
+        value_delimiter = ',',
+        env = "XP_MCP_TRANSPORT"
+    )]
+    transport: Option<Vec<TransportType>>,
+
+    /// Bind address for the HTTP server (used with sse and http transports) [default: 127.0.0.1:8000]
+    #[arg(short, long, env = "XP_MCP_BIND_ADDRESS")]
+    bind_address: Option<String>,
+
+    /// Bind address for the streamable HTTP server [default: --bind-address]
+    #[arg(long, env = "XP_MCP_HTTP_BIND_ADDRESS")]
+    http_bind_address: Option<String>,
+
+    /// Bind address for the WebSocket server [default: --bind-address]
+    #[arg(long, env = "XP_MCP_WS_BIND_ADDRESS")]
+    ws_bind_address: Option<String>,
+
+    /// Seconds a streamable HTTP session may go without an open stream or a request before it is ended [default: 300]
+    #[arg(long, env = "XP_MCP_HTTP_IDLE_TIMEOUT", value_parser = clap::value_parser!(u64).range(1..))]
+    http_idle_timeout: Option<u64>,
+
+    /// Largest WebSocket message (and frame) accepted from a client, in bytes [default: 1048576]
+    #[arg(long, env = "XP_MCP_WS_MAX_MESSAGE_SIZE")]
+    ws_max_message_size: Option<usize>,
+
+    /// Seconds between WebSocket pings; clients that miss one are disconnected [default: 30]
+    #[arg(long, env = "XP_MCP_WS_PING_INTERVAL", value_parser = clap::value_parser!(u64).range(1..))]
+    ws_ping_interval: Option<u64>,
+
+    /// Path of the Unix domain socket (used with the unix transport) [default: xp-mcp.sock]
+    #[arg(long, env = "XP_MCP_SOCKET_PATH")]
+    socket_path: Option<PathBuf>,
+
+    /// Octal permission bits for the Unix socket file; 600 limits it to the current user [default: 600]
+    #[arg(long, env = "XP_MCP_SOCKET_MODE", value_parser = config::parse_socket_mode)]
+    socket_mode: Option<u32>,
+
+    /// Log level or filter directives, e.g. "debug" or "info,rmcp=warn" [default: $RUST_LOG, or info]
+    #[arg(short, long, env = "XP_MCP_LOG_LEVEL", value_parser = logging::parse_directives)]
+    log_level: Option<String>,
+
+    /// Log output format [default: full]
+    #[arg(long, value_enum, env = "XP_MCP_LOG_FORMAT")]
+    log_format: Option<LogFormat>,
+
+    /// Where to write logs: stdout, stderr, none, or a file path [default: stderr for stdio transport, stdout otherwise]
+    #[arg(long, env = "XP_MCP_LOG_TARGET", value_parser = LogTarget::parse)]
+    log_target: Option<LogTarget>,
+
+    /// File of name=token lines; when set (or XP_MCP_AUTH_TOKENS is), HTTP requests need a matching bearer token
+    #[arg(long, env = "XP_MCP_AUTH_TOKENS_FILE")]
+    auth_tokens_file: Option<PathBuf>,
+
+    /// Comma-separated name=token pairs accepted as bearer tokens
+    #[arg(long, env = "XP_MCP_AUTH_TOKENS", hide_env_values = true)]
+    auth_tokens: Option<String>,
+
+    /// PEM certificate chain; with --tls-key, serves the HTTP transports over HTTPS (reloaded on SIGHUP)
+    #[arg(long, env = "XP_MCP_TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key matching --tls-cert
+    #[arg(long, env = "XP_MCP_TLS_KEY")]
+    tls_key: Option<PathBuf>,
+
+    /// Whether counter state is shared server-wide or isolated per session [default: shared]
+    #[arg(short, long, value_enum, env = "XP_MCP_STATE_MODE")]
+    state_mode: Option<StateMode>,
+
+    /// JSON file used to persist counter values across restarts (shared state mode only)
+    #[arg(long, env = "XP_MCP_STATE_FILE")]
+    state_file: Option<PathBuf>,
+
+    /// JSON file the memo tools keep their memos in, shared by every session [default: xp-mcp-memos.json]
+    #[arg(long, env = "XP_MCP_MEMO_FILE")]
+    memo_file: Option<PathBuf>,
+
+    /// How counter arithmetic behaves when it would overflow a 64-bit integer [default: error]
+    #[arg(long, value_enum, env = "XP_MCP_OVERFLOW")]
+    overflow: Option<OverflowPolicy>,
+
+    /// Seconds to let in-flight requests finish after a shutdown signal [default: 30]
+    #[arg(long, env = "XP_MCP_SHUTDOWN_TIMEOUT")]
+    shutdown_timeout: Option<u64>,
+
+    /// Most items returned per page of a tools, prompts, resources or resource templates list [default: 100]
+    #[arg(long, env = "XP_MCP_PAGE_SIZE", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    page_size: Option<usize>,
+
+    /// Comma-separated tools clients may call [default: all]
+    #[arg(long, value_delimiter = ',', env = "XP_MCP_TOOLS")]
+    tools: Option<Vec<String>>,
+
+    /// Directory whose files are served as file:// resources; repeat or separate with commas for several [default: none]
+    #[arg(long = "file-root", value_delimiter = ',', env = "XP_MCP_FILE_ROOTS")]
+    file_roots: Option<Vec<PathBuf>>,
+
+    /// Largest file served as a resource, in bytes [default: 1048576]
+    #[arg(long, env = "XP_MCP_FILE_MAX_SIZE")]
+    file_max_size: Option<u64>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Command {
+    /// Inspect the configuration
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Connect to a server and send it one request
+    Client(client::ClientArgs),
+    /// Open an interactive shell on a server, with completion and history
+    Repl(repl::ReplArgs),
 }
 
-#[derive(Debug, Clone, ValueEnum)]
+#[derive(Subcommand, Debug, Clone)]
+enum ConfigCommand {
+    /// Validate the configuration and print the effective settings as TOML
+    Check,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum TransportType {
     /// Use standard input/output for transport
     Stdio,
     /// Use Server-Sent Events over HTTP for transport
     Sse,
+    /// Use the streamable HTTP transport on a single /mcp endpoint
+    Http,
+    /// Use one JSON-RPC message per WebSocket text message on a /ws endpoint
+    #[value(name = "websocket")]
+    WebSocket,
+    /// Use newline-delimited JSON-RPC over a Unix domain socket
+    Unix,
+}
+
+impl TransportType {
+    /// Name used on the command line, in `/info` and as the metrics label.
+    fn name(self) -> &'static str {
+        match self {
+            Self::Stdio => "stdio",
+            Self::Sse => "sse",
+            Self::Http => "http",
+            Self::WebSocket => "websocket",
+            Self::Unix => "unix",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum StateMode {
+    /// All sessions (SSE and stdio) share a single counter store
+    Shared,
+    /// Every session gets its own fresh counter store
+    Session,
 }
 
 /// Usage:
 /// - For SSE (default): cargo run
 /// - For SSE with custom address: cargo run -- -b 0.0.0.0:9000
 /// - For stdio: cargo run -- --transport stdio
+/// - For streamable HTTP: cargo run -- --transport http
+/// - For WebSocket: cargo run -- --transport websocket
+/// - For a Unix socket: cargo run -- --transport unix --socket-path /tmp/xp-mcp.sock
+/// - For SSE and stdio at once: cargo run -- --transport sse,stdio
 /// - Set log level: cargo run -- --log-level debug
+/// - Filter per module: cargo run -- --log-level info,rmcp=warn
+/// - Structured logs: cargo run -- --log-format json
+/// - Log to a file: cargo run -- --log-target server.log
+/// - Isolate state per session: cargo run -- --state-mode session
+/// - Persist counters: cargo run -- --state-file counters.json
+/// - Keep memos elsewhere: cargo run -- --memo-file /var/lib/xp-mcp/memos.json
+/// - Clamp instead of failing on overflow: cargo run -- --overflow saturate
+/// - Require bearer tokens: cargo run -- --auth-tokens-file tokens.txt
+/// - Serve HTTPS: cargo run -- --tls-cert cert.pem --tls-key key.pem
+/// - Probe the server: curl http://127.0.0.1:8000/readyz
+/// - Scrape metrics: curl http://127.0.0.1:8000/metrics
+/// - Wait up to 5s for requests on shutdown: cargo run -- --shutdown-timeout 5
+/// - Load settings from a file: cargo run -- --config xp-mcp.toml
+/// - Show the effective settings: cargo run -- --config xp-mcp.toml config check
+/// - Apply edits to the file while running: cargo run -- --config xp-mcp.toml --watch-config
+/// - Serve a directory's files as resources: cargo run -- --file-root ./docs
+/// - Call a tool on a running server: cargo run -- client --sse http://127.0.0.1:8000/sse call increment
+/// - Poke at a running server by hand: cargo run -- repl --sse http://127.0.0.1:8000/sse
 #[tokio::main]
-async fn main() -> Result<()> {
-    // Parse command line arguments
-    let args = Args::parse();
-
-    // Initialize tracing subscriber with simple format
-    let level = match args.log_level.as_str() {
-        "trace" => tracing::Level::TRACE,
-        "debug" => tracing::Level::DEBUG,
-        "info" => tracing::Level::INFO,
-        "warn" => tracing::Level::WARN,
-        "error" => tracing::Level::ERROR,
-        _ => tracing::Level::INFO,
-    };
+async fn main() -> Result<ExitCode> {
+    // Parse command line arguments, then layer them over the config file
+    let mut args = Args::parse();
+    let command = args.command.take();
+    match command {
+        Some(Command::Client(client)) => return client::run(client).await,
+        Some(Command::Repl(repl)) => return repl::run(repl).await,
+        _ => {}
+    }
+    let config = Config::resolve(args.clone())?;
+    if let Some(Command::Config(ConfigCommand::Check)) = command {
+        return check_config(&config).await;
+    }
+    let transports = &config.transport;
 
-    tracing_subscriber::fmt().with_max_level(level).init();
+    let log = logging::init(&config.log_level, config.log_format, &config.log_target)?;
 
     info!("Starting RMCP server");
-    debug!(transport = ?args.transport, bind_address = %args.bind_address, "Parsed command line arguments");
-
-    match args.transport {
-        TransportType::Stdio => {
-            info!("Using stdio transport");
-
-            // Create and serve the counter over stdio
-            debug!("Initializing Counter service with stdio transport");
-            let service = Counter::new()
-                .serve(stdio())
-                .await
-                .inspect_err(|e| error!("Failed to serve Counter over stdio: {:?}", e))?;
-
-            info!("Service initialized, waiting for completion");
-            service.waiting().await?;
-            info!("Service completed");
-        }
-        TransportType::Sse => {
-            info!("Using SSE transport (default)");
-
-            // Parse bind address
-            debug!("Parsing bind address: {}", args.bind_address);
-            let addr: SocketAddr = match args.bind_address.parse() {
-                Ok(addr) => addr,
-                Err(e) => {
-                    error!("Failed to parse bind address: {}", e);
-                    return Err(e.into());
+    debug!(transports = ?transports, bind_address = %config.bind_address, state_mode = ?config.state_mode, "Resolved configuration");
+
+    // Server-wide counter store, handed to every session in shared mode
+    let store = match &config.state_file {
+        Some(path) => CounterStore::load(path)
+            .inspect_err(|e| error!("Failed to load state file {}: {}", path.display(), e))?,
+        None => CounterStore::new(),
+    }
+    .with_overflow(config.overflow);
+
+    // Memos are shared whatever the state mode, so sessions can leave notes for each other
+    let memos = MemoStore::load(&config.memo_file).inspect_err(|e| {
+        error!(
+            "Failed to load memo file {}: {}",
+            config.memo_file.display(),
+            e
+        )
+    })?;
+
+    // Tracks in-flight requests across every session so shutdown can wait for them
+    let in_flight = TaskTracker::new();
+    let status = ServerStatus::new(
+        transports
+            .iter()
+            .map(|transport| transport.name())
+            .collect(),
+        Metrics::new(
+            // Session counters are private to each session, so there's nothing server-wide to report
+            matches!(config.state_mode, StateMode::Shared).then(|| store.clone()),
+            Counter::tool_names(),
+        ),
+    );
+
+    // Shared by every session so a reload reaches all of them at once
+    let catalog = LiveCatalog::new(config.catalog.clone());
+
+    // Builds the Counter handed to each new session
+    let new_session = {
+        let store = store.clone();
+        let memos = memos.clone();
+        let page_size = config.page_size;
+        let state_mode = config.state_mode;
+        let overflow = config.overflow;
+        let catalog = catalog.clone();
+        let in_flight = in_flight.clone();
+        let status = status.clone();
+        move |transport: &'static str| {
+            let counter = match state_mode {
+                StateMode::Shared => Counter::with_store(store.clone()),
+                StateMode::Session => {
+                    Counter::with_store(CounterStore::new().with_overflow(overflow))
                 }
             };
+            // Stamped on the memos this session writes
+            let session_id = format!("{transport}-{:08x}", rand::random::<u32>());
+            Session::new(
+                counter
+                    .with_catalog(catalog.clone())
+                    .with_memos(memos.clone(), session_id)
+                    .with_page_size(page_size),
+                in_flight.clone(),
+                status.session_opened(transport),
+            )
+        }
+    };
+
+    let auth = TokenAuth::from_sources(
+        config.auth_tokens_file.as_deref(),
+        config.auth_tokens.as_deref(),
+    )?;
+    match &auth {
+        Some(auth) => info!(tokens = auth.len(), "Bearer token authentication enabled"),
+        None => debug!("Authentication disabled"),
+    }
+
+    // Every listener and session hangs off this token so one cancel stops them all;
+    // listeners can be stopped on their own first so shutdown lets sessions drain
+    let ct = CancellationToken::new();
+    let listener_ct = ct.child_token();
+    let tasks = TaskTracker::new();
+
+    let tls = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => {
+            let tls = TlsConfig::load(cert, key).await?;
+            tls.reload_on_sighup(ct.child_token())?;
+            info!(cert = %cert.display(), "TLS enabled, send SIGHUP to reload the certificate");
+            Some(tls)
+        }
+        _ => None,
+    };
+    let mut stdio_task = None;
 
-            // Create and serve the counter over SSE
-            info!("Starting SSE server on {}", addr);
-            let ct = match SseServer::serve(addr).await {
-                Ok(server) => {
-                    debug!("SSE server started successfully");
-                    server.with_service(Counter::new)
+    for transport in transports {
+        match transport {
+            TransportType::Stdio => {
+                info!("Using stdio transport");
+                stdio_task = Some(tokio::spawn(serve_stdio(
+                    new_session(transport.name()),
+                    ct.child_token(),
+                )));
+            }
+            TransportType::Sse => {
+                info!("Using SSE transport");
+
+                let addr = config.bind_address;
+
+                // Create and serve the counter over SSE
+                info!("Starting SSE server on {}", addr);
+                let config = SseServerConfig {
+                    bind: addr,
+                    sse_path: "/sse".to_string(),
+                    post_path: "/message".to_string(),
+                    ct: ct.child_token(),
+                    listener_ct: listener_ct.clone(),
+                    tasks: tasks.clone(),
+                    auth: auth.clone(),
+                    tls: tls.clone(),
+                    status: status.clone(),
+                };
+                match SseServer::serve_with_config(config).await {
+                    Ok(server) => {
+                        debug!("SSE server started successfully");
+                        let new_session = new_session.clone();
+                        server.with_service(move || new_session(TransportType::Sse.name()));
+                    }
+                    Err(e) => {
+                        error!("Failed to start SSE server: {:?}", e);
+                        return Err(e.into());
+                    }
                 }
-                Err(e) => {
-                    error!("Failed to start SSE server: {:?}", e);
-                    return Err(e.into());
+            }
+            TransportType::Http => {
+                info!("Using streamable HTTP transport");
+
+                let addr = config.http_bind_address;
+
+                // Create and serve the counter over streamable HTTP
+                info!("Starting streamable HTTP server on {}/mcp", addr);
+                let config = StreamableHttpConfig {
+                    bind: addr,
+                    path: "/mcp".to_string(),
+                    ct: ct.child_token(),
+                    listener_ct: listener_ct.clone(),
+                    tasks: tasks.clone(),
+                    auth: auth.clone(),
+                    tls: tls.clone(),
+                    status: status.clone(),
+                    idle_timeout: Duration::from_secs(config.http_idle_timeout),
+                };
+                match StreamableHttpServer::serve_with_config(config).await {
+                    Ok(server) => {
+                        debug!("Streamable HTTP server started successfully");
+                        let new_session = new_session.clone();
+                        server.with_service(move || new_session(TransportType::Http.name()));
+                    }
+                    Err(e) => {
+                        error!("Failed to start streamable HTTP server: {:?}", e);
+                        return Err(e.into());
+                    }
                 }
-            };
+            }
+            TransportType::WebSocket => {
+                info!("Using WebSocket transport");
+
+                let addr = config.ws_bind_address;
+
+                // Create and serve the counter over WebSocket
+                info!("Starting WebSocket server on {}/ws", addr);
+                let config = WebSocketConfig {
+                    bind: addr,
+                    path: "/ws".to_string(),
+                    ct: ct.child_token(),
+                    listener_ct: listener_ct.clone(),
+                    tasks: tasks.clone(),
+                    auth: auth.clone(),
+                    tls: tls.clone(),
+                    status: status.clone(),
+                    max_message_size: config.ws_max_message_size,
+                    ping_interval: Duration::from_secs(config.ws_ping_interval),
+                };
+                match WebSocketServer::serve_with_config(config).await {
+                    Ok(server) => {
+                        debug!("WebSocket server started successfully");
+                        let new_session = new_session.clone();
+                        server.with_service(move || new_session(TransportType::WebSocket.name()));
+                    }
+                    Err(e) => {
+                        error!("Failed to start WebSocket server: {:?}", e);
+                        return Err(e.into());
+                    }
+                }
+            }
+            #[cfg(unix)]
+            TransportType::Unix => {
+                info!("Using Unix socket transport");
+
+                // Create and serve the counter over a Unix domain socket
+                info!(
+                    "Starting Unix socket server on {}",
+                    config.socket_path.display()
+                );
+                let config = UnixSocketConfig {
+                    path: config.socket_path.clone(),
+                    mode: config.socket_mode,
+                    ct: ct.child_token(),
+                    listener_ct: listener_ct.clone(),
+                    tasks: tasks.clone(),
+                };
+                match UnixSocketServer::serve_with_config(config).await {
+                    Ok(server) => {
+                        debug!("Unix socket server started successfully");
+                        let new_session = new_session.clone();
+                        server.with_service(move || new_session(TransportType::Unix.name()));
+                    }
+                    Err(e) => {
+                        error!("Failed to start Unix socket server: {:?}", e);
+                        return Err(e.into());
+                    }
+                }
+            }
+            #[cfg(not(unix))]
+            TransportType::Unix => {
+                anyhow::bail!("the unix transport is only available on Unix platforms")
+            }
+        }
+    }
+
+    // SIGHUP reloads the config file and the certificate when there are any,
+    // otherwise it stops the server too
+    if let Some(path) = &args.config {
+        let watch = args.watch_config.then(|| path.clone());
+        let reloader = Reloader::new(args.clone(), config.clone(), catalog, log);
+        let ct = listener_ct.clone();
+        tasks.spawn(async move {
+            if let Err(e) = reloader.run(watch, ct).await {
+                error!("Configuration reloading stopped: {:?}", e);
+            }
+        });
+        info!(path = %path.display(), "Send SIGHUP to reload prompts, resources, tools and log level");
+    }
+    let hangup = tls.is_none() && args.config.is_none();
+
+    status.set_ready(true);
 
-            // Wait for Ctrl+C signal
+    // With stdio alone, the process lives as long as that session does;
+    // otherwise the network listeners keep it running until a signal arrives.
+    let stdio_only = transports.len() == 1 && stdio_task.is_some();
+    match stdio_task {
+        Some(task) if stdio_only => {
+            tokio::select! {
+                result = task => {
+                    result??;
+                    info!("Stdio session ended");
+                }
+                signal = shutdown::signal(hangup) => info!("Received {}", signal?),
+            }
+        }
+        other => {
+            if let Some(task) = other {
+                tokio::spawn(async move {
+                    match task.await {
+                        Ok(Ok(())) => info!("Stdio session ended, other transports keep running"),
+                        Ok(Err(e)) => error!("Stdio session failed: {:?}", e),
+                        Err(e) => error!("Stdio task panicked: {:?}", e),
+                    }
+                });
+            }
             info!("Server running, press Ctrl+C to stop");
-            tokio::signal::ctrl_c().await?;
-            info!("Shutting down SSE server");
-            ct.cancel();
-            info!("Server shutdown complete");
+            // Not inside info!, which skips its arguments when logging is off
+            let signal = shutdown::signal(hangup).await?;
+            info!("Received {}", signal);
         }
     }
 
+    // Stop taking new sessions, then give in-flight requests a chance to finish
+    info!("Shutting down");
+    status.set_ready(false);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(config.shutdown_timeout);
+    listener_ct.cancel();
+    in_flight.close();
+    let drained = tokio::select! {
+        _ = in_flight.wait() => true,
+        _ = tokio::time::sleep_until(deadline) => {
+            warn!(in_flight = in_flight.len(), "Shutdown timeout elapsed, abandoning in-flight requests");
+            false
+        }
+        signal = shutdown::signal(hangup) => {
+            warn!(in_flight = in_flight.len(), "Received {} while draining, abandoning in-flight requests", signal?);
+            false
+        }
+    };
+    if drained {
+        tokio::time::sleep(shutdown::RESPONSE_GRACE).await;
+    }
+
+    // End the sessions and wait for transports to deliver what they were last given
+    ct.cancel();
+    tasks.close();
+    let closed = tokio::time::timeout_at(deadline, tasks.wait())
+        .await
+        .is_ok();
+    if !closed {
+        warn!(
+            tasks = tasks.len(),
+            "Shutdown timeout elapsed before all connections closed"
+        );
+    }
+    store.flush().await?;
+    info!("Server shutdown complete");
+
     info!("RMCP server exiting");
+    Ok(if drained && closed {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(shutdown::EXIT_DRAIN_TIMEOUT)
+    })
+}
+
+/// Serve one session over stdin/stdout until the client disconnects.
+async fn serve_stdio(counter: impl Service<RoleServer>, ct: CancellationToken) -> Result<()> {
+    // Create and serve the counter over stdio
+    debug!("Initializing Counter service with stdio transport");
+    let service = counter
+        .serve_with_ct(stdio(), ct)
+        .await
+        .inspect_err(|e| error!("Failed to serve Counter over stdio: {:?}", e))?;
+
+    info!("Service initialized, waiting for completion");
+    service.waiting().await?;
+    info!("Service completed");
     Ok(())
 }
+
+/// Validate everything the configuration refers to without serving anything, then print it.
+async fn check_config(config: &Config) -> Result<ExitCode> {
+    TokenAuth::from_sources(
+        config.auth_tokens_file.as_deref(),
+        config.auth_tokens.as_deref(),
+    )?;
+    if let (Some(cert), Some(key)) = (&config.tls_cert, &config.tls_key) {
+        TlsConfig::load(cert, key).await?;
+    }
+    if let Some(path) = &config.state_file {
+        CounterStore::load(path)
+            .with_context(|| format!("failed to load state file {}", path.display()))?;
+    }
+    MemoStore::load(&config.memo_file)
+        .with_context(|| format!("failed to load memo file {}", config.memo_file.display()))?;
+    print!("{}", config.to_toml()?);
+    Ok(ExitCode::SUCCESS)
+}
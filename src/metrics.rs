@@ -0,0 +1,261 @@
+//! Prometheus metrics for requests, tool calls, sessions and counter values.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::{
+    Router,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use prometheus_client::encoding::{EncodeLabelSet, text::encode};
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::metrics::family::Family;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::metrics::histogram::{Histogram, exponential_buckets};
+use prometheus_client::registry::Registry;
+use rmcp::model::ClientRequest;
+
+use crate::common::store::CounterStore;
+
+const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Label used for tool names the server doesn't know, so clients can't
+/// create unbounded label sets by calling made-up tools.
+const UNKNOWN_TOOL: &str = "unknown";
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RequestLabels {
+    method: &'static str,
+    transport: &'static str,
+    outcome: &'static str,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct RequestDurationLabels {
+    method: &'static str,
+    transport: &'static str,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ToolLabels {
+    tool: String,
+    transport: &'static str,
+    outcome: &'static str,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct ToolDurationLabels {
+    tool: String,
+    transport: &'static str,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct TransportLabels {
+    transport: &'static str,
+}
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct CounterLabels {
+    name: String,
+}
+
+type Histograms<L> = Family<L, Histogram, fn() -> Histogram>;
+
+fn duration_histogram() -> Histogram {
+    // 0.5ms .. ~16s
+    Histogram::new(exponential_buckets(0.0005, 2.0, 16))
+}
+
+/// Server-wide metrics registry, cheap to clone.
+#[derive(Clone, Debug)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    registry: Mutex<Registry>,
+    requests: Family<RequestLabels, Counter>,
+    request_duration: Histograms<RequestDurationLabels>,
+    tool_calls: Family<ToolLabels, Counter>,
+    tool_duration: Histograms<ToolDurationLabels>,
+    sessions: Family<TransportLabels, Gauge>,
+    counter_values: Family<CounterLabels, Gauge>,
+    known_tools: HashSet<String>,
+    /// Absent in session mode, where every session has its own counters
+    store: Option<CounterStore>,
+}
+
+impl Metrics {
+    /// `store`, if given, is sampled for counter values on every scrape.
+    pub fn new(store: Option<CounterStore>, known_tools: impl IntoIterator<Item = String>) -> Self {
+        let requests = Family::<RequestLabels, Counter>::default();
+        let request_duration = Histograms::new_with_constructor(duration_histogram as fn() -> _);
+        let tool_calls = Family::<ToolLabels, Counter>::default();
+        let tool_duration = Histograms::new_with_constructor(duration_histogram as fn() -> _);
+        let sessions = Family::<TransportLabels, Gauge>::default();
+        let counter_values = Family::<CounterLabels, Gauge>::default();
+
+        let mut registry = Registry::with_prefix("mcp");
+        registry.register(
+            "requests",
+            "MCP requests handled, by method",
+            requests.clone(),
+        );
+        registry.register(
+            "request_duration_seconds",
+            "Time spent handling MCP requests",
+            request_duration.clone(),
+        );
+        registry.register("tool_calls", "Tool invocations", tool_calls.clone());
+        registry.register(
+            "tool_call_duration_seconds",
+            "Time spent in tool invocations",
+            tool_duration.clone(),
+        );
+        registry.register(
+            "sessions_active",
+            "Sessions currently open",
+            sessions.clone(),
+        );
+        if store.is_some() {
+            registry.register(
+                "counter_value",
+                "Current value of each shared counter",
+                counter_values.clone(),
+            );
+        }
+
+        Self {
+            inner: Arc::new(Inner {
+                registry: Mutex::new(registry),
+                requests,
+                request_duration,
+                tool_calls,
+                tool_duration,
+                sessions,
+                counter_values,
+                known_tools: known_tools.into_iter().collect(),
+                store,
+            }),
+        }
+    }
+
+    pub fn session_opened(&self, transport: &'static str) {
+        self.inner
+            .sessions
+            .get_or_create(&TransportLabels { transport })
+            .inc();
+    }
+
+    pub fn session_closed(&self, transport: &'static str) {
+        self.inner
+            .sessions
+            .get_or_create(&TransportLabels { transport })
+            .dec();
+    }
+
+    /// Record one handled request; `tool` is set for `tools/call`.
+    pub fn observe_request(
+        &self,
+        transport: &'static str,
+        method: &'static str,
+        tool: Option<&str>,
+        elapsed: Duration,
+        ok: bool,
+    ) {
+        let outcome = if ok { "ok" } else { "error" };
+        self.inner
+            .requests
+            .get_or_create(&RequestLabels {
+                method,
+                transport,
+                outcome,
+            })
+            .inc();
+        self.inner
+            .request_duration
+            .get_or_create(&RequestDurationLabels { method, transport })
+            .observe(elapsed.as_secs_f64());
+
+        if let Some(tool) = tool {
+            let tool = if self.inner.known_tools.contains(tool) {
+                tool
+            } else {
+                UNKNOWN_TOOL
+            };
+            self.inner
+                .tool_calls
+                .get_or_create(&ToolLabels {
+                    tool: tool.to_string(),
+                    transport,
+                    outcome,
+                })
+                .inc();
+            self.inner
+                .tool_duration
+                .get_or_create(&ToolDurationLabels {
+                    tool: tool.to_string(),
+                    transport,
+                })
+                .observe(elapsed.as_secs_f64());
+        }
+    }
+
+    /// Encode every metric in the OpenMetrics text format.
+    pub async fn render(&self) -> Result<String, std::fmt::Error> {
+        let counters = match &self.inner.store {
+            Some(store) => store.list().await,
+            None => Default::default(),
+        };
+        // Held across the refresh so concurrent scrapes don't see a half-filled family
+        let registry = self.inner.registry.lock().expect("metrics lock poisoned");
+        self.inner.counter_values.clear();
+        for (name, value) in counters {
+            self.inner
+                .counter_values
+                .get_or_create(&CounterLabels { name })
+                .set(value);
+        }
+        let mut body = String::new();
+        encode(&mut body, &registry)?;
+        Ok(body)
+    }
+}
+
+/// The MCP method name of a request, as sent on the wire.
+pub fn method_name(request: &ClientRequest) -> &'static str {
+    match request {
+        ClientRequest::PingRequest(_) => "ping",
+        ClientRequest::InitializeRequest(_) => "initialize",
+        ClientRequest::CompleteRequest(_) => "completion/complete",
+        ClientRequest::SetLevelRequest(_) => "logging/setLevel",
+        ClientRequest::GetPromptRequest(_) => "prompts/get",
+        ClientRequest::ListPromptsRequest(_) => "prompts/list",
+        ClientRequest::ListResourcesRequest(_) => "resources/list",
+        ClientRequest::ListResourceTemplatesRequest(_) => "resources/templates/list",
+        ClientRequest::ReadResourceRequest(_) => "resources/read",
+        ClientRequest::SubscribeRequest(_) => "resources/subscribe",
+        ClientRequest::UnsubscribeRequest(_) => "resources/unsubscribe",
+        ClientRequest::CallToolRequest(_) => "tools/call",
+        ClientRequest::ListToolsRequest(_) => "tools/list",
+    }
+}
+
+/// `/metrics` in the Prometheus text exposition format.
+pub fn router(metrics: Metrics) -> Router {
+    Router::new()
+        .route("/metrics", get(scrape))
+        .with_state(metrics)
+}
+
+async fn scrape(State(metrics): State<Metrics>) -> Response {
+    match metrics.render().await {
+        Ok(body) => ([(header::CONTENT_TYPE, CONTENT_TYPE)], body).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
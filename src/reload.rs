@@ -0,0 +1,127 @@
+//! Applying configuration changes to a running server.
+//!
+//! Prompts, resources, tool enablement and the log level take effect without
+//! a restart; sessions stay connected and are sent `list_changed`
+//! notifications. Every other setting is only read at startup.
+
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use tokio::io;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::Args;
+use crate::common::catalog::LiveCatalog;
+use crate::config::Config;
+use crate::logging::LogHandle;
+
+/// How often a watched config file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Re-reads the layered configuration and applies the reloadable parts.
+pub struct Reloader {
+    /// The command line and environment as parsed at startup, which still win over the file
+    args: Args,
+    applied: Config,
+    catalog: LiveCatalog,
+    log: LogHandle,
+}
+
+impl Reloader {
+    pub fn new(args: Args, applied: Config, catalog: LiveCatalog, log: LogHandle) -> Self {
+        Self {
+            args,
+            applied,
+            catalog,
+            log,
+        }
+    }
+
+    /// Reload on SIGHUP, and whenever `watch` changes if set, until `ct` is cancelled.
+    pub async fn run(mut self, watch: Option<PathBuf>, ct: CancellationToken) -> io::Result<()> {
+        let mut hangup = hangup()?;
+        let mut modified = watch.as_deref().and_then(modified_time);
+        let mut interval = tokio::time::interval(WATCH_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = ct.cancelled() => return Ok(()),
+                _ = hangup.recv() => info!("Received SIGHUP, reloading configuration"),
+                _ = interval.tick(), if watch.is_some() => {
+                    let now = watch.as_deref().and_then(modified_time);
+                    if now == modified {
+                        continue;
+                    }
+                    modified = now;
+                    info!("Configuration file changed, reloading");
+                }
+            }
+            self.reload();
+        }
+    }
+
+    /// Validate the whole configuration first, so a bad edit changes nothing.
+    fn reload(&mut self) {
+        let mut config = match Config::resolve(self.args.clone()) {
+            Ok(config) => config,
+            Err(e) => {
+                error!("Keeping the current configuration, reload failed: {:#}", e);
+                return;
+            }
+        };
+
+        for setting in self.applied.restart_required(&config) {
+            warn!(
+                setting,
+                "Setting changed but only takes effect after a restart"
+            );
+        }
+        if config.log_level != self.applied.log_level {
+            match self.log.set_directives(&config.log_level) {
+                Ok(()) => info!(log_level = %config.log_level, "Log level changed"),
+                Err(e) => {
+                    error!("Failed to change log level: {:#}", e);
+                    config.log_level = self.applied.log_level.clone();
+                }
+            }
+        }
+        let changes = self.catalog.replace(config.catalog.clone());
+        if changes.any() {
+            info!(
+                tools = changes.tools,
+                resources = changes.resources,
+                prompts = changes.prompts,
+                "Catalog changed, notifying sessions"
+            );
+        } else {
+            debug!("Catalog unchanged");
+        }
+        self.applied = self.applied.keep_restart_only(config);
+    }
+}
+
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(unix)]
+fn hangup() -> io::Result<tokio::signal::unix::Signal> {
+    use tokio::signal::unix::{SignalKind, signal};
+    signal(SignalKind::hangup())
+}
+
+/// Without SIGHUP, only a watched file triggers reloads.
+#[cfg(not(unix))]
+fn hangup() -> io::Result<NoSignal> {
+    Ok(NoSignal)
+}
+
+#[cfg(not(unix))]
+struct NoSignal;
+
+#[cfg(not(unix))]
+impl NoSignal {
+    async fn recv(&mut self) -> Option<()> {
+        std::future::pending().await
+    }
+}
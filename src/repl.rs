@@ -0,0 +1,326 @@
+//! An interactive shell on top of the client.
+//!
+//! Keeps one connection open, completes tool, parameter, resource and prompt
+//! names from what the server lists, and remembers history across runs.
+
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow, bail};
+use rmcp::model::*;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{CompletionType, Config, Editor, Helper};
+
+use crate::client::{self, ConnectArgs, Connection};
+
+/// Built-in commands; anything else is taken as a tool name.
+const COMMANDS: [(&str, &str); 9] = [
+    ("tools", "list tools and their parameters"),
+    (
+        "call",
+        "<tool> [key=value]...  call a tool; `call` may be left out",
+    ),
+    ("resources", "list resources"),
+    ("read", "<uri>  read a resource"),
+    ("prompts", "list prompts and their arguments"),
+    ("prompt", "<name> [key=value]...  render a prompt"),
+    ("json", "toggle printing raw JSON results"),
+    ("help", "show this list"),
+    ("quit", "leave (or Ctrl-D)"),
+];
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ReplArgs {
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+
+    /// File to keep command history in [default: ~/.xp_mcp_history]
+    #[arg(long, env = "XP_MCP_HISTORY")]
+    pub history: Option<PathBuf>,
+}
+
+/// Run the `repl` subcommand until the user quits.
+pub async fn run(args: ReplArgs) -> Result<ExitCode> {
+    let connection = client::connect(&args.connect).await?;
+    let server = &connection.peer().peer_info().server_info;
+    println!(
+        "Connected to {} {}. Type help for commands, Ctrl-D to quit.",
+        server.name, server.version
+    );
+
+    let config = Config::builder()
+        .completion_type(CompletionType::List)
+        .auto_add_history(true)
+        .build();
+    let mut editor = Editor::with_config(config)?;
+    editor.set_helper(Some(Names::default()));
+    let history = args.history.or_else(|| {
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".xp_mcp_history"))
+    });
+    if let Some(path) = &history {
+        // A missing file just means there is no history yet
+        let _ = editor.load_history(path);
+    }
+
+    let mut repl = Repl {
+        connection,
+        editor,
+        timeout: Duration::from_secs(args.connect.timeout),
+        json: false,
+    };
+    repl.refresh().await;
+    let result = repl.run().await;
+
+    if let Some(path) = &history
+        && let Err(e) = repl.editor.save_history(path)
+    {
+        eprintln!("Could not save history to {}: {}", path.display(), e);
+    }
+    repl.connection.cancel().await?;
+    result.map(|()| ExitCode::SUCCESS)
+}
+
+struct Repl {
+    connection: Connection,
+    editor: Editor<Names, DefaultHistory>,
+    timeout: Duration,
+    json: bool,
+}
+
+impl Repl {
+    async fn run(&mut self) -> Result<()> {
+        loop {
+            let line = match tokio::task::block_in_place(|| self.editor.readline("mcp> ")) {
+                Ok(line) => line,
+                // Ctrl-C abandons the line, Ctrl-D leaves
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => return Ok(()),
+                Err(e) => return Err(e.into()),
+            };
+            let Some(words) = shlex::split(&line) else {
+                eprintln!("error: unbalanced quotes");
+                continue;
+            };
+            match words.first().map(String::as_str) {
+                None => {}
+                Some("quit" | "exit") => return Ok(()),
+                Some(_) => {
+                    let done = tokio::time::timeout(self.timeout, self.execute(words));
+                    match done.await {
+                        Ok(Ok(())) => {}
+                        Ok(Err(e)) => eprintln!("error: {e:#}"),
+                        Err(_) => {
+                            eprintln!("error: no response within {}s", self.timeout.as_secs())
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    async fn execute(&mut self, words: Vec<String>) -> Result<()> {
+        let peer = self.connection.peer().clone();
+        let (command, rest) = words.split_first().expect("line is not empty");
+        match (command.as_str(), rest) {
+            ("help", _) => {
+                for (command, description) in COMMANDS {
+                    println!("  {command:<10} {description}");
+                }
+            }
+            ("json", _) => {
+                self.json = !self.json;
+                println!("JSON output {}", if self.json { "on" } else { "off" });
+            }
+            ("tools", _) => {
+                let tools = peer.list_all_tools().await?;
+                client::output(self.json, &tools, client::print_tools)?;
+                self.names().tools = tools;
+            }
+            ("resources", _) => {
+                let resources = peer.list_all_resources().await?;
+                client::output(self.json, &resources, client::print_resources)?;
+                self.names().resources = resources.into_iter().map(|r| r.raw.uri).collect();
+            }
+            ("prompts", _) => {
+                let prompts = peer.list_all_prompts().await?;
+                client::output(self.json, &prompts, client::print_prompts)?;
+                self.names().prompts = prompts;
+            }
+            ("read", [uri]) => {
+                let result = peer
+                    .read_resource(ReadResourceRequestParam { uri: uri.clone() })
+                    .await?;
+                client::output(self.json, &result, client::print_resource_contents)?;
+            }
+            ("read", _) => bail!("usage: read <uri>"),
+            ("prompt", [name, args @ ..]) => {
+                let result = peer
+                    .get_prompt(GetPromptRequestParam {
+                        name: name.clone(),
+                        arguments: Some(client::prompt_arguments(key_values(args)?)),
+                    })
+                    .await?;
+                client::output(self.json, &result, client::print_prompt)?;
+            }
+            ("prompt", _) => bail!("usage: prompt <name> [key=value]..."),
+            ("call", [tool, args @ ..]) => self.call(tool, args).await?,
+            ("call", _) => bail!("usage: call <tool> [key=value]..."),
+            (tool, args) => {
+                if !self.names().tools.iter().any(|t| t.name == tool) {
+                    bail!(
+                        "unknown command or tool {tool:?}; type help for commands, tools for tools"
+                    );
+                }
+                self.call(tool, args).await?
+            }
+        }
+        Ok(())
+    }
+
+    async fn call(&mut self, tool: &str, args: &[String]) -> Result<()> {
+        let schema = self
+            .names()
+            .tools
+            .iter()
+            .find(|t| t.name == tool)
+            .map(|t| t.input_schema.clone());
+        let arguments = client::tool_arguments(schema.as_deref(), key_values(args)?);
+        let result = self
+            .connection
+            .peer()
+            .call_tool(CallToolRequestParam {
+                name: tool.to_string().into(),
+                arguments: Some(arguments),
+            })
+            .await?;
+        client::output(self.json, &result, client::print_call_result)
+    }
+
+    /// Fetch what there is to complete; servers without resources or prompts
+    /// just leave those empty.
+    async fn refresh(&mut self) {
+        let peer = self.connection.peer().clone();
+        let names = Names {
+            tools: peer.list_all_tools().await.unwrap_or_default(),
+            resources: peer
+                .list_all_resources()
+                .await
+                .unwrap_or_default()
+                .into_iter()
+                .map(|r| r.raw.uri)
+                .collect(),
+            prompts: peer.list_all_prompts().await.unwrap_or_default(),
+        };
+        *self.names() = names;
+    }
+
+    fn names(&mut self) -> &mut Names {
+        self.editor.helper_mut().expect("helper is set")
+    }
+}
+
+fn key_values(args: &[String]) -> Result<Vec<(String, String)>> {
+    args.iter()
+        .map(|arg| client::parse_key_value(arg).map_err(|e| anyhow!(e)))
+        .collect()
+}
+
+/// What the server offers, for tab completion.
+#[derive(Default)]
+struct Names {
+    tools: Vec<Tool>,
+    resources: Vec<String>,
+    prompts: Vec<Prompt>,
+}
+
+impl Names {
+    fn parameters(&self, tool: &str) -> Vec<String> {
+        self.tools
+            .iter()
+            .find(|t| t.name == tool)
+            .and_then(|t| t.input_schema.get("properties")?.as_object())
+            .map(|properties| properties.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    fn arguments(&self, prompt: &str) -> Vec<String> {
+        self.prompts
+            .iter()
+            .find(|p| p.name == prompt)
+            .and_then(|p| p.arguments.as_ref())
+            .map(|arguments| arguments.iter().map(|a| a.name.clone()).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Completer for Names {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &before[start..];
+        let words: Vec<&str> = before[..start].split_whitespace().collect();
+
+        let tools = || self.tools.iter().map(|t| t.name.to_string());
+        let (candidates, assignments): (Vec<String>, &[&str]) = match words.as_slice() {
+            [] => (
+                COMMANDS
+                    .iter()
+                    .map(|(c, _)| c.to_string())
+                    .chain(tools())
+                    .collect(),
+                &[],
+            ),
+            ["call"] => (tools().collect(), &[]),
+            ["read"] => (self.resources.clone(), &[]),
+            ["prompt"] => (self.prompts.iter().map(|p| p.name.clone()).collect(), &[]),
+            ["prompt", name, given @ ..] => (self.arguments(name), given),
+            ["call", tool, given @ ..] | [tool, given @ ..] => (self.parameters(tool), given),
+        };
+
+        // Parameters complete as `name=`, skipping those already on the line
+        let parameters =
+            !words.is_empty() && !matches!(words.as_slice(), ["call" | "read" | "prompt"]);
+        let pairs = candidates
+            .into_iter()
+            .filter(|c| c.starts_with(word))
+            .filter(|c| {
+                !parameters
+                    || !assignments
+                        .iter()
+                        .any(|a| a.split('=').next() == Some(c.as_str()))
+            })
+            .map(|c| Pair {
+                replacement: if parameters {
+                    format!("{c}=")
+                } else {
+                    format!("{c} ")
+                },
+                display: c,
+            })
+            .collect();
+        Ok((start, pairs))
+    }
+}
+
+impl Hinter for Names {
+    type Hint = String;
+}
+
+impl Highlighter for Names {}
+
+impl Validator for Names {}
+
+impl Helper for Names {}
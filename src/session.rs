@@ -0,0 +1,80 @@
+//! Server-wide bookkeeping wrapped around each session's MCP service.
+
+use std::time::Instant;
+
+use rmcp::model::{ClientNotification, ClientRequest, ServerInfo, ServerResult};
+use rmcp::service::{Peer, RequestContext};
+use rmcp::{Error as McpError, RoleServer, Service};
+use tokio_util::task::TaskTracker;
+
+use crate::metrics;
+use crate::status::SessionGuard;
+
+/// Wraps one session's service so the server can account for it.
+///
+/// Each request holds a token on `in_flight` until its response is ready, so
+/// shutdown can close the tracker and wait for it to empty, and is recorded in
+/// the metrics under the session's transport. The session counts as active
+/// until the service is dropped.
+pub struct Session<S> {
+    inner: S,
+    in_flight: TaskTracker,
+    open: SessionGuard,
+}
+
+impl<S> Session<S> {
+    pub fn new(inner: S, in_flight: TaskTracker, open: SessionGuard) -> Self {
+        Self {
+            inner,
+            in_flight,
+            open,
+        }
+    }
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for Session<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, McpError> {
+        let _in_flight = self.in_flight.token();
+        let method = metrics::method_name(&request);
+        let tool = match &request {
+            ClientRequest::CallToolRequest(call) => Some(call.params.name.to_string()),
+            _ => None,
+        };
+        let started = Instant::now();
+        let result = self.inner.handle_request(request, context).await;
+        // A tool reporting `is_error` still answers the request, but counts as a failed call
+        let ok = match &result {
+            Ok(ServerResult::CallToolResult(result)) => result.is_error != Some(true),
+            Ok(_) => true,
+            Err(_) => false,
+        };
+        self.open.metrics().observe_request(
+            self.open.transport(),
+            method,
+            tool.as_deref(),
+            started.elapsed(),
+            ok,
+        );
+        result
+    }
+
+    async fn handle_notification(&self, notification: ClientNotification) -> Result<(), McpError> {
+        self.inner.handle_notification(notification).await
+    }
+
+    fn get_peer(&self) -> Option<Peer<RoleServer>> {
+        self.inner.get_peer()
+    }
+
+    fn set_peer(&mut self, peer: Peer<RoleServer>) {
+        self.inner.set_peer(peer);
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        self.inner.get_info()
+    }
+}
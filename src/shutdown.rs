@@ -0,0 +1,48 @@
+//! Signal handling for graceful shutdown.
+
+use std::time::Duration;
+
+use tokio::io;
+
+/// Exit status when the shutdown timeout elapsed with requests or connections still open.
+pub const EXIT_DRAIN_TIMEOUT: u8 = 3;
+
+/// Pause between the last request finishing and sessions being cancelled.
+///
+/// rmcp hands a response to the session's serve loop only after the handler
+/// returns, and a cancelled loop may exit before forwarding it.
+pub const RESPONSE_GRACE: Duration = Duration::from_millis(100);
+
+/// Wait for a termination signal and return its name.
+///
+/// SIGTERM and SIGINT always count; SIGHUP only when `hangup` is set, since
+/// it is otherwise reserved for reloading.
+#[cfg(unix)]
+pub async fn signal(hangup: bool) -> io::Result<&'static str> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut hup = if hangup {
+        Some(signal(SignalKind::hangup())?)
+    } else {
+        None
+    };
+    let hup = async {
+        match &mut hup {
+            Some(hup) => hup.recv().await,
+            None => std::future::pending().await,
+        }
+    };
+    Ok(tokio::select! {
+        _ = terminate.recv() => "SIGTERM",
+        _ = interrupt.recv() => "SIGINT",
+        _ = hup => "SIGHUP",
+    })
+}
+
+#[cfg(not(unix))]
+pub async fn signal(_hangup: bool) -> io::Result<&'static str> {
+    tokio::signal::ctrl_c().await?;
+    Ok("Ctrl+C")
+}
@@ -0,0 +1,143 @@
+//! Health, readiness and info endpoints for supervisors and load balancers.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use rmcp::model::Implementation;
+use serde::Serialize;
+
+use crate::metrics::{self, Metrics};
+
+/// Server-wide liveness facts shared by every transport.
+#[derive(Clone, Debug)]
+pub struct ServerStatus {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    started: Instant,
+    transports: Vec<&'static str>,
+    ready: AtomicBool,
+    sessions: AtomicUsize,
+    metrics: Metrics,
+}
+
+impl ServerStatus {
+    pub fn new(transports: Vec<&'static str>, metrics: Metrics) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                started: Instant::now(),
+                transports,
+                ready: AtomicBool::new(false),
+                sessions: AtomicUsize::new(0),
+                metrics,
+            }),
+        }
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        &self.inner.metrics
+    }
+
+    /// Ready once every transport is listening, and no longer once shutdown starts.
+    pub fn set_ready(&self, ready: bool) {
+        self.inner.ready.store(ready, Ordering::Relaxed);
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.inner.ready.load(Ordering::Relaxed)
+    }
+
+    /// Count a session on `transport` as active until the returned guard is dropped.
+    pub fn session_opened(&self, transport: &'static str) -> SessionGuard {
+        self.inner.sessions.fetch_add(1, Ordering::Relaxed);
+        self.inner.metrics.session_opened(transport);
+        SessionGuard {
+            status: self.clone(),
+            transport,
+        }
+    }
+
+    pub fn sessions(&self) -> usize {
+        self.inner.sessions.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+pub struct SessionGuard {
+    status: ServerStatus,
+    transport: &'static str,
+}
+
+impl SessionGuard {
+    pub fn transport(&self) -> &'static str {
+        self.transport
+    }
+
+    pub fn metrics(&self) -> &Metrics {
+        self.status.metrics()
+    }
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        self.status.inner.sessions.fetch_sub(1, Ordering::Relaxed);
+        self.status.inner.metrics.session_closed(self.transport);
+    }
+}
+
+/// `/healthz` and `/readyz`, meant to stay reachable without credentials.
+pub fn probe_router(status: ServerStatus) -> Router {
+    Router::new()
+        .route("/healthz", get(|| async { "ok" }))
+        .route("/readyz", get(readyz))
+        .with_state(status)
+}
+
+/// `/info` and `/metrics`, which reveal version and load details and so sit behind auth.
+pub fn admin_router(status: ServerStatus) -> Router {
+    Router::new()
+        .route("/info", get(info))
+        .with_state(status.clone())
+        .merge(metrics::router(status.metrics().clone()))
+}
+
+async fn readyz(State(status): State<ServerStatus>) -> (StatusCode, &'static str) {
+    if status.is_ready() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "not ready")
+    }
+}
+
+/// This server's name and version, as reported by `initialize` and `/info`.
+pub fn implementation() -> Implementation {
+    // `Implementation::from_build_env` expands inside rmcp and reports its version, not ours
+    Implementation {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+}
+
+#[derive(Serialize)]
+struct Info {
+    #[serde(flatten)]
+    server: Implementation,
+    uptime_seconds: u64,
+    active_sessions: usize,
+    transports: Vec<&'static str>,
+    ready: bool,
+}
+
+async fn info(State(status): State<ServerStatus>) -> Json<Info> {
+    Json(Info {
+        server: implementation(),
+        uptime_seconds: status.inner.started.elapsed().as_secs(),
+        active_sessions: status.sessions(),
+        transports: status.inner.transports.clone(),
+        ready: status.is_ready(),
+    })
+}
@@ -0,0 +1,135 @@
+//! Static bearer-token authentication for the HTTP transports.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, bail};
+use axum::{
+    extract::{Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{Instrument, debug, info_span, warn};
+
+/// Named bearer tokens accepted by the HTTP transports.
+#[derive(Clone, Debug)]
+pub struct TokenAuth {
+    tokens: Arc<Vec<NamedToken>>,
+}
+
+#[derive(Debug)]
+struct NamedToken {
+    name: String,
+    token: String,
+}
+
+impl TokenAuth {
+    /// Build the token set from a file and/or an inline list.
+    ///
+    /// Returns `None` when neither source is given, meaning authentication is off.
+    pub fn from_sources(file: Option<&Path>, inline: Option<&str>) -> Result<Option<Self>> {
+        if file.is_none() && inline.is_none() {
+            return Ok(None);
+        }
+        let mut tokens = Vec::new();
+        if let Some(path) = file {
+            let source = std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read auth tokens from {}", path.display()))?;
+            parse_entries(&source, &mut tokens)
+                .with_context(|| format!("invalid auth tokens in {}", path.display()))?;
+        }
+        if let Some(source) = inline {
+            parse_entries(source, &mut tokens)?;
+        }
+        if tokens.is_empty() {
+            bail!("authentication enabled but no tokens configured");
+        }
+        Ok(Some(Self {
+            tokens: Arc::new(tokens),
+        }))
+    }
+
+    pub fn len(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Name of the client owning `presented`, if it is a known token.
+    fn authenticate(&self, presented: &str) -> Option<&str> {
+        // Check every token so timing doesn't reveal which one nearly matched
+        self.tokens
+            .iter()
+            .fold(None, |found, candidate| {
+                let matched = constant_time_eq(candidate.token.as_bytes(), presented.as_bytes());
+                found.or(matched.then_some(candidate))
+            })
+            .map(|token| token.name.as_str())
+    }
+}
+
+/// Parse `name=token` entries separated by newlines or commas.
+///
+/// Blank entries and lines starting with `#` are ignored.
+fn parse_entries(source: &str, tokens: &mut Vec<NamedToken>) -> Result<()> {
+    for entry in source.split(['\n', ',']).map(str::trim) {
+        if entry.is_empty() || entry.starts_with('#') {
+            continue;
+        }
+        let Some((name, token)) = entry.split_once('=') else {
+            bail!("auth token entries must look like name=token");
+        };
+        let (name, token) = (name.trim(), token.trim());
+        if name.is_empty() || token.is_empty() {
+            bail!("auth token entries need both a name and a token");
+        }
+        tokens.push(NamedToken {
+            name: name.to_string(),
+            token: token.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Axum middleware rejecting requests without a valid `Authorization: Bearer` header.
+///
+/// Accepted requests run inside a span carrying the token's name so every
+/// log line for the request says which client made it.
+pub async fn require_bearer(
+    State(auth): State<TokenAuth>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match presented.and_then(|token| auth.authenticate(token)) {
+        Some(client) => {
+            let span = info_span!("auth", client = %client);
+            debug!(parent: &span, method = %request.method(), path = %request.uri().path(), "authenticated request");
+            next.run(request).instrument(span).await
+        }
+        None => {
+            warn!(
+                path = %request.uri().path(),
+                has_token = presented.is_some(),
+                "rejected unauthenticated request"
+            );
+            (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                "unauthorized",
+            )
+                .into_response()
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::net::SocketAddr;
+
+use axum::{Router, middleware};
+use futures::SinkExt;
+use futures::future::BoxFuture;
+use rmcp::model::{
+    ClientJsonRpcMessage, ClientNotification, ClientRequest, ProtocolVersion, ServerInfo,
+    ServerJsonRpcMessage, ServerResult,
+};
+use rmcp::service::{Peer, RequestContext};
+use rmcp::transport::IntoTransport;
+use rmcp::{Error as McpError, RoleServer, Service, ServiceExt};
+use tokio::io;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::{CancellationToken, PollSender};
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, Span, error, info};
+
+use crate::status::{self, ServerStatus};
+use auth::TokenAuth;
+use tls::TlsConfig;
+
+pub mod auth;
+pub mod sse;
+pub mod streamable_http;
+pub mod tls;
+#[cfg(unix)]
+pub mod unix;
+pub mod websocket;
+
+/// Channel halves connecting one session of an HTTP transport to its MCP
+/// service, and the token that ends the service.
+struct ChannelTransport {
+    to_client: mpsc::Sender<ServerJsonRpcMessage>,
+    from_client: mpsc::Receiver<ClientJsonRpcMessage>,
+    ct: CancellationToken,
+    /// Answer `initialize` with this version instead of the service's own
+    protocol_version: Option<ProtocolVersion>,
+    /// Runs once the service has ended
+    on_close: Option<BoxFuture<'static, ()>>,
+}
+
+impl ChannelTransport {
+    fn new(
+        to_client: mpsc::Sender<ServerJsonRpcMessage>,
+        from_client: mpsc::Receiver<ClientJsonRpcMessage>,
+        ct: CancellationToken,
+    ) -> Self {
+        Self {
+            to_client,
+            from_client,
+            ct,
+            protocol_version: None,
+            on_close: None,
+        }
+    }
+
+    fn protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.protocol_version = Some(protocol_version);
+        self
+    }
+
+    fn on_close(mut self, f: impl Future<Output = ()> + Send + 'static) -> Self {
+        self.on_close = Some(Box::pin(f));
+        self
+    }
+}
+
+/// A session's service, reporting the protocol version its transport
+/// negotiated with the client, if any, in place of its own.
+struct Negotiated<S> {
+    inner: S,
+    protocol_version: Option<ProtocolVersion>,
+}
+
+impl<S: Service<RoleServer>> Service<RoleServer> for Negotiated<S> {
+    async fn handle_request(
+        &self,
+        request: ClientRequest,
+        context: RequestContext<RoleServer>,
+    ) -> Result<ServerResult, McpError> {
+        self.inner.handle_request(request, context).await
+    }
+
+    async fn handle_notification(&self, notification: ClientNotification) -> Result<(), McpError> {
+        self.inner.handle_notification(notification).await
+    }
+
+    fn get_peer(&self) -> Option<Peer<RoleServer>> {
+        self.inner.get_peer()
+    }
+
+    fn set_peer(&mut self, peer: Peer<RoleServer>) {
+        self.inner.set_peer(peer);
+    }
+
+    fn get_info(&self) -> ServerInfo {
+        let mut info = self.inner.get_info();
+        if let Some(protocol_version) = &self.protocol_version {
+            info.protocol_version = protocol_version.clone();
+        }
+        info
+    }
+}
+
+/// Finish an HTTP transport's `routes` with the admin and probe routes.
+///
+/// With `auth` set every route but the probes requires a bearer token.
+fn with_status_routes(routes: Router, status: &ServerStatus, auth: Option<&TokenAuth>) -> Router {
+    let mut router = routes.merge(status::admin_router(status.clone()));
+    if let Some(auth) = auth {
+        router = router.layer(middleware::from_fn_with_state(
+            auth.clone(),
+            auth::require_bearer,
+        ));
+    }
+    // Probes are merged after the auth layer so they stay reachable without a token
+    router.merge(status::probe_router(status.clone()))
+}
+
+/// Serve a fresh service from `service_provider` on every transport received
+/// from `transports`, each as a task on `tasks`.
+fn spawn_sessions<S, F>(
+    mut transports: mpsc::UnboundedReceiver<ChannelTransport>,
+    service_provider: F,
+    tasks: TaskTracker,
+) where
+    S: Service<RoleServer>,
+    F: Fn() -> S + Send + 'static,
+{
+    tokio::spawn(async move {
+        while let Some(transport) = transports.recv().await {
+            let ChannelTransport {
+                to_client,
+                from_client,
+                ct,
+                protocol_version,
+                on_close,
+            } = transport;
+            let transport = (
+                PollSender::new(to_client).sink_map_err(io::Error::other),
+                ReceiverStream::new(from_client),
+            );
+            let service = Negotiated {
+                inner: service_provider(),
+                protocol_version,
+            };
+            tasks.spawn(async move {
+                let result = run_session(service, transport, ct).await;
+                if let Some(on_close) = on_close {
+                    on_close.await;
+                }
+                result
+            });
+        }
+    });
+}
+
+/// Bind `bind` and serve `router` in the background until `ct` is cancelled.
+///
+/// With `tls` set the listener speaks HTTPS using that (reloadable) certificate.
+/// The server runs on `tasks`, which finishes once open connections are closed.
+async fn serve_router(
+    bind: SocketAddr,
+    router: Router,
+    ct: CancellationToken,
+    span: Span,
+    tls: Option<TlsConfig>,
+    tasks: &TaskTracker,
+) -> io::Result<()> {
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    match tls {
+        None => {
+            let server = axum::serve(listener, router).with_graceful_shutdown(async move {
+                ct.cancelled().await;
+                info!("server cancelled");
+            });
+            tasks.spawn(
+                async move {
+                    if let Err(e) = server.await {
+                        error!(error = %e, "server shutdown with error");
+                    }
+                }
+                .instrument(span),
+            );
+        }
+        Some(tls) => {
+            let handle = axum_server::Handle::new();
+            let server = axum_server::from_tcp_rustls(listener.into_std()?, tls.rustls())
+                .handle(handle.clone())
+                .serve(router.into_make_service());
+            tokio::spawn(async move {
+                ct.cancelled().await;
+                info!("server cancelled");
+                handle.graceful_shutdown(None);
+            });
+            tasks.spawn(
+                async move {
+                    if let Err(e) = server.await {
+                        error!(error = %e, "server shutdown with error");
+                    }
+                }
+                .instrument(span),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Serve one session on `transport` until the client leaves or `ct` is cancelled.
+///
+/// rmcp only watches `ct` after the `initialize` handshake, so a client that
+/// connects and never initializes would otherwise hold up shutdown.
+async fn run_session<S, T, A>(service: S, transport: T, ct: CancellationToken) -> io::Result<()>
+where
+    S: Service<RoleServer>,
+    T: IntoTransport<RoleServer, io::Error, A>,
+{
+    let session = async {
+        let server = service.serve_with_ct(transport, ct.clone()).await?;
+        server.waiting().await?;
+        io::Result::Ok(())
+    };
+    tokio::select! {
+        result = session => result,
+        _ = ct.cancelled() => Ok(()),
+    }
+}
@@ -0,0 +1,210 @@
+//! SSE transport (MCP 2024-11-05).
+//!
+//! Clients open a `GET` event stream, receive an `endpoint` event naming the
+//! URL to `POST` their messages to, and get every server message back as a
+//! `message` event. This mirrors `rmcp::transport::sse_server`, but owns its
+//! router so the listener can carry middleware such as authentication.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{
+        Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::{get, post},
+};
+use futures::{Stream, StreamExt};
+use rmcp::{RoleServer, Service, model::ClientJsonRpcMessage};
+use tokio::io;
+use tokio::sync::{RwLock, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{info, warn};
+
+use super::ChannelTransport;
+use super::auth::TokenAuth;
+use super::tls::TlsConfig;
+use crate::status::ServerStatus;
+
+const CHANNEL_CAPACITY: usize = 64;
+
+type SessionId = Arc<str>;
+type TxStore = Arc<RwLock<HashMap<SessionId, mpsc::Sender<ClientJsonRpcMessage>>>>;
+
+#[derive(Debug, Clone)]
+pub struct SseServerConfig {
+    pub bind: SocketAddr,
+    pub sse_path: String,
+    pub post_path: String,
+    /// Ends every session served by this listener
+    pub ct: CancellationToken,
+    /// Stops accepting connections while existing sessions keep running
+    pub listener_ct: CancellationToken,
+    /// Runs the listener and its sessions so shutdown can wait for them to finish
+    pub tasks: TaskTracker,
+    /// Require a bearer token on every request when set
+    pub auth: Option<TokenAuth>,
+    /// Serve HTTPS with this certificate when set
+    pub tls: Option<TlsConfig>,
+    /// Backs the `/healthz`, `/readyz`, `/info` and `/metrics` routes
+    pub status: ServerStatus,
+}
+
+#[derive(Debug)]
+pub struct SseServer {
+    transport_rx: mpsc::UnboundedReceiver<ChannelTransport>,
+    pub config: SseServerConfig,
+}
+
+impl SseServer {
+    pub async fn serve_with_config(config: SseServerConfig) -> io::Result<Self> {
+        let (transport_tx, transport_rx) = mpsc::unbounded_channel();
+        let app = App {
+            txs: Default::default(),
+            transport_tx,
+            post_path: config.post_path.clone().into(),
+            ct: config.ct.clone(),
+        };
+        let router = super::with_status_routes(
+            Router::new()
+                .route(&config.sse_path, get(sse_handler))
+                .route(&config.post_path, post(post_event_handler))
+                .with_state(app),
+            &config.status,
+            config.auth.as_ref(),
+        );
+        super::serve_router(
+            config.bind,
+            router,
+            config.listener_ct.clone(),
+            tracing::info_span!("sse-server", bind_address = %config.bind),
+            config.tls.clone(),
+            &config.tasks,
+        )
+        .await?;
+        Ok(Self {
+            transport_rx,
+            config,
+        })
+    }
+
+    /// Serve a fresh service from `service_provider` for every new session.
+    pub fn with_service<S, F>(self, service_provider: F) -> CancellationToken
+    where
+        S: Service<RoleServer>,
+        F: Fn() -> S + Send + 'static,
+    {
+        super::spawn_sessions(self.transport_rx, service_provider, self.config.tasks);
+        self.config.ct
+    }
+}
+
+#[derive(Clone)]
+struct App {
+    txs: TxStore,
+    transport_tx: mpsc::UnboundedSender<ChannelTransport>,
+    post_path: Arc<str>,
+    /// Parent of every session's token
+    ct: CancellationToken,
+}
+
+fn session_id() -> SessionId {
+    Arc::from(format!("{:016x}", rand::random::<u128>()))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PostEventQuery {
+    session_id: String,
+}
+
+async fn post_event_handler(
+    State(app): State<App>,
+    Query(PostEventQuery { session_id }): Query<PostEventQuery>,
+    Json(message): Json<ClientJsonRpcMessage>,
+) -> Result<StatusCode, StatusCode> {
+    tracing::debug!(session_id, ?message, "new client message");
+    let tx = {
+        let rg = app.txs.read().await;
+        rg.get(session_id.as_str())
+            .ok_or(StatusCode::NOT_FOUND)?
+            .clone()
+    };
+    if tx.send(message).await.is_err() {
+        tracing::error!("send message error");
+        return Err(StatusCode::GONE);
+    }
+    Ok(StatusCode::ACCEPTED)
+}
+
+async fn sse_handler(
+    State(app): State<App>,
+) -> Result<Sse<impl Stream<Item = Result<Event, io::Error>>>, Response<String>> {
+    let session = session_id();
+    info!(%session, "sse connection");
+    let (from_client_tx, from_client_rx) = mpsc::channel(CHANNEL_CAPACITY);
+    let (to_client_tx, to_client_rx) = mpsc::channel(CHANNEL_CAPACITY);
+    app.txs
+        .write()
+        .await
+        .insert(session.clone(), from_client_tx);
+    let txs = app.txs.clone();
+    let closed = session.clone();
+    let transport = ChannelTransport::new(to_client_tx, from_client_rx, app.ct.child_token())
+        .on_close(async move {
+            txs.write().await.remove(&closed);
+        });
+    if app.transport_tx.send(transport).is_err() {
+        warn!("send transport out error");
+        let mut response =
+            Response::new("fail to send out transport, it seems server is closed".to_string());
+        *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+        return Err(response);
+    }
+    let post_path = app.post_path.as_ref();
+    let endpoint = format!("{post_path}?sessionId={session}");
+    let disconnect = Disconnect {
+        txs: app.txs.clone(),
+        session,
+    };
+    let stream = futures::stream::once(futures::future::ok(
+        Event::default().event("endpoint").data(endpoint),
+    ))
+    .chain(ReceiverStream::new(to_client_rx).map(move |message| {
+        // The stream owns the guard, so the session ends when the client goes away
+        let _ = &disconnect;
+        match serde_json::to_string(&message) {
+            Ok(bytes) => Ok(Event::default().event("message").data(&bytes)),
+            Err(e) => Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }));
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// Closes a session's inbound channel once its event stream is dropped.
+///
+/// Without this a client that disconnects leaves its service waiting for
+/// messages that can no longer arrive.
+struct Disconnect {
+    txs: TxStore,
+    session: SessionId,
+}
+
+impl Drop for Disconnect {
+    fn drop(&mut self) {
+        let txs = self.txs.clone();
+        let session = self.session.clone();
+        tokio::spawn(async move {
+            if txs.write().await.remove(&session).is_some() {
+                info!(%session, "sse client disconnected");
+            }
+        });
+    }
+}
@@ -0,0 +1,552 @@
+//! Streamable HTTP transport (MCP 2025-03-26).
+//!
+//! A single endpoint accepts `POST` for client messages, `GET` for the
+//! server-initiated event stream and `DELETE` to end a session. Sessions are
+//! identified by the `Mcp-Session-Id` header assigned on `initialize`. Every
+//! SSE event carries a session-wide id so a client that loses a stream can
+//! reconnect with `GET` and `Last-Event-ID` to receive what it missed.
+//!
+//! Clients often just disconnect instead of sending `DELETE`, so a session
+//! with no open stream that hasn't seen a request for the configured idle
+//! timeout is ended as if it had been deleted.
+
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode},
+    response::{
+        IntoResponse, Response,
+        sse::{Event, KeepAlive, Sse},
+    },
+    routing::get,
+};
+use futures::{Stream, StreamExt};
+use rmcp::{
+    RoleServer, Service,
+    model::{
+        ClientJsonRpcMessage, ClientRequest, JsonRpcMessage, ProtocolVersion, RequestId,
+        ServerJsonRpcMessage,
+    },
+};
+use tokio::io;
+use tokio::sync::{RwLock, mpsc};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{debug, error, info, warn};
+
+use super::ChannelTransport;
+use super::auth::TokenAuth;
+use super::tls::TlsConfig;
+use crate::status::ServerStatus;
+
+const SESSION_ID_HEADER: &str = "mcp-session-id";
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+/// MCP revisions a session on this transport can speak, newest first. The
+/// first defines this transport; rmcp has no constant for it yet.
+const PROTOCOL_VERSIONS: [&str; 2] = ["2025-03-26", "2024-11-05"];
+
+/// Events kept per session for `Last-Event-ID` replay.
+const HISTORY_LIMIT: usize = 1024;
+const CHANNEL_CAPACITY: usize = 64;
+
+type SessionId = Arc<str>;
+type Sessions = Arc<RwLock<HashMap<SessionId, Arc<Session>>>>;
+
+/// The version to answer an `initialize` asking for `requested` with: the
+/// client's own if we speak it, otherwise the newest we do.
+fn negotiate(requested: &ProtocolVersion) -> ProtocolVersion {
+    let supported = PROTOCOL_VERSIONS.map(|version| {
+        serde_json::from_value::<ProtocolVersion>(version.into())
+            .expect("protocol version is a string")
+    });
+    supported
+        .iter()
+        .find(|version| *version == requested)
+        .unwrap_or(&supported[0])
+        .clone()
+}
+
+#[derive(Debug, Clone)]
+pub struct StreamableHttpConfig {
+    pub bind: SocketAddr,
+    pub path: String,
+    /// Ends every session served by this listener
+    pub ct: CancellationToken,
+    /// Stops accepting connections while existing sessions keep running
+    pub listener_ct: CancellationToken,
+    /// Runs the listener and its sessions so shutdown can wait for them to finish
+    pub tasks: TaskTracker,
+    /// Require a bearer token on every request when set
+    pub auth: Option<TokenAuth>,
+    /// Serve HTTPS with this certificate when set
+    pub tls: Option<TlsConfig>,
+    /// Backs the `/healthz`, `/readyz`, `/info` and `/metrics` routes
+    pub status: ServerStatus,
+    /// End sessions that have had no open stream and no request for this long
+    pub idle_timeout: Duration,
+}
+
+#[derive(Debug)]
+pub struct StreamableHttpServer {
+    transport_rx: mpsc::UnboundedReceiver<ChannelTransport>,
+    pub config: StreamableHttpConfig,
+}
+
+impl StreamableHttpServer {
+    pub async fn serve_with_config(config: StreamableHttpConfig) -> io::Result<Self> {
+        let (transport_tx, transport_rx) = mpsc::unbounded_channel();
+        let app = App {
+            sessions: Default::default(),
+            transport_tx,
+            ct: config.ct.clone(),
+        };
+        tokio::spawn(expire_idle(
+            app.sessions.clone(),
+            config.idle_timeout,
+            config.ct.clone(),
+        ));
+        let router = super::with_status_routes(
+            Router::new()
+                .route(
+                    &config.path,
+                    get(get_handler).post(post_handler).delete(delete_handler),
+                )
+                .with_state(app),
+            &config.status,
+            config.auth.as_ref(),
+        );
+        super::serve_router(
+            config.bind,
+            router,
+            config.listener_ct.clone(),
+            tracing::info_span!("streamable-http-server", bind_address = %config.bind),
+            config.tls.clone(),
+            &config.tasks,
+        )
+        .await?;
+        Ok(Self {
+            transport_rx,
+            config,
+        })
+    }
+
+    /// Serve a fresh service from `service_provider` for every new session.
+    pub fn with_service<S, F>(self, service_provider: F) -> CancellationToken
+    where
+        S: Service<RoleServer>,
+        F: Fn() -> S + Send + 'static,
+    {
+        super::spawn_sessions(self.transport_rx, service_provider, self.config.tasks);
+        self.config.ct
+    }
+}
+
+#[derive(Clone)]
+struct App {
+    sessions: Sessions,
+    transport_tx: mpsc::UnboundedSender<ChannelTransport>,
+    /// Parent of every session's token
+    ct: CancellationToken,
+}
+
+/// Which SSE stream an outgoing message belongs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum StreamKey {
+    /// The long-lived stream opened with `GET`
+    Standalone,
+    /// The response stream of one `POST`
+    Post(u64),
+}
+
+#[derive(Debug, Clone)]
+struct StoredEvent {
+    id: u64,
+    stream: StreamKey,
+    data: Arc<str>,
+}
+
+struct Session {
+    to_service: mpsc::Sender<ClientJsonRpcMessage>,
+    streams: Arc<Mutex<Streams>>,
+    ct: CancellationToken,
+}
+
+impl Session {
+    fn lock(&self) -> std::sync::MutexGuard<'_, Streams> {
+        self.streams.lock().expect("streams lock poisoned")
+    }
+
+    /// Stop the service; its dispatch task then finishes on its own.
+    fn end(&self) {
+        self.ct.cancel();
+    }
+}
+
+#[derive(Default)]
+struct Streams {
+    next_event_id: u64,
+    next_post_stream: u64,
+    history: VecDeque<StoredEvent>,
+    /// Currently connected SSE responses
+    live: HashMap<StreamKey, mpsc::UnboundedSender<StoredEvent>>,
+    /// Stream each unanswered request's response should go to
+    pending: HashMap<RequestId, StreamKey>,
+    /// Unanswered request count per `POST` stream; the stream closes at zero
+    outstanding: HashMap<StreamKey, usize>,
+    /// Last request, or last sweep that found a stream open
+    last_active: Option<Instant>,
+}
+
+impl Streams {
+    fn touch(&mut self) {
+        self.last_active = Some(Instant::now());
+    }
+
+    /// Whether the session has gone `timeout` without an open stream or a request.
+    fn idle(&mut self, timeout: Duration) -> bool {
+        // A dropped response leaves its sender behind until something is sent on it
+        self.live.retain(|_, tx| !tx.is_closed());
+        if !self.live.is_empty() {
+            self.touch();
+            return false;
+        }
+        self.last_active
+            .is_some_and(|last_active| last_active.elapsed() >= timeout)
+    }
+
+    /// Open a response stream for the requests in one `POST`.
+    fn open_post_stream(
+        &mut self,
+        request_ids: Vec<RequestId>,
+    ) -> mpsc::UnboundedReceiver<StoredEvent> {
+        let key = StreamKey::Post(self.next_post_stream);
+        self.next_post_stream += 1;
+        self.outstanding.insert(key, request_ids.len());
+        for id in request_ids {
+            self.pending.insert(id, key);
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        self.live.insert(key, tx);
+        rx
+    }
+
+    /// Route one outgoing message to its stream, recording it for replay.
+    fn dispatch(&mut self, message: &ServerJsonRpcMessage) -> serde_json::Result<()> {
+        let key = match message {
+            JsonRpcMessage::Response(response) => self.pending.remove(&response.id),
+            JsonRpcMessage::Error(error) => self.pending.remove(&error.id),
+            _ => None,
+        }
+        .unwrap_or(StreamKey::Standalone);
+
+        let event = StoredEvent {
+            id: self.next_event_id,
+            stream: key,
+            data: serde_json::to_string(message)?.into(),
+        };
+        self.next_event_id += 1;
+        if self.history.len() == HISTORY_LIMIT {
+            self.history.pop_front();
+        }
+        self.history.push_back(event.clone());
+
+        if let Some(tx) = self.live.get(&key)
+            && tx.send(event).is_err()
+        {
+            // Client went away; the event stays in history for resumption
+            self.live.remove(&key);
+        }
+
+        if let Some(outstanding) = self.outstanding.get_mut(&key) {
+            *outstanding -= 1;
+            if *outstanding == 0 {
+                self.outstanding.remove(&key);
+                self.live.remove(&key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Reconnect the stream that carried `last_event_id`, replaying later events.
+    fn resume(&mut self, last_event_id: Option<u64>) -> mpsc::UnboundedReceiver<StoredEvent> {
+        let key = last_event_id
+            .and_then(|last| self.history.iter().find(|event| event.id == last))
+            .map(|event| event.stream)
+            .unwrap_or(StreamKey::Standalone);
+        let (tx, rx) = mpsc::unbounded_channel();
+        if let Some(last) = last_event_id {
+            for event in self
+                .history
+                .iter()
+                .filter(|e| e.stream == key && e.id > last)
+            {
+                let _ = tx.send(event.clone());
+            }
+        }
+        // A finished POST stream has nothing more to say, so let it close after the replay
+        if key == StreamKey::Standalone || self.outstanding.contains_key(&key) {
+            self.live.insert(key, tx);
+        }
+        rx
+    }
+}
+
+fn session_id() -> SessionId {
+    Arc::from(format!("{:032x}", rand::random::<u128>()))
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response {
+    (status, message.to_string()).into_response()
+}
+
+fn header_session(headers: &HeaderMap) -> Option<&str> {
+    headers.get(SESSION_ID_HEADER)?.to_str().ok()
+}
+
+async fn find_session(app: &App, headers: &HeaderMap) -> Result<Arc<Session>, Response> {
+    let Some(id) = header_session(headers) else {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "missing Mcp-Session-Id header",
+        ));
+    };
+    app.sessions
+        .read()
+        .await
+        .get(id)
+        .cloned()
+        .ok_or_else(|| error_response(StatusCode::NOT_FOUND, "unknown session"))
+}
+
+fn sse_response(
+    rx: mpsc::UnboundedReceiver<StoredEvent>,
+) -> Sse<impl Stream<Item = Result<Event, io::Error>>> {
+    let stream = UnboundedReceiverStream::new(rx).map(|event| {
+        Ok(Event::default()
+            .id(event.id.to_string())
+            .event("message")
+            .data(event.data.as_ref()))
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Parse a single message or a JSON-RPC batch.
+fn parse_messages(body: &[u8]) -> serde_json::Result<Vec<ClientJsonRpcMessage>> {
+    match serde_json::from_slice::<serde_json::Value>(body)? {
+        serde_json::Value::Array(batch) => batch
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<Result<_, _>>(),
+        single => Ok(vec![serde_json::from_value(single)?]),
+    }
+}
+
+/// The protocol version asked for, if `message` is an `initialize` request.
+fn requested_version(message: &ClientJsonRpcMessage) -> Option<&ProtocolVersion> {
+    match message {
+        JsonRpcMessage::Request(request) => match &request.request {
+            ClientRequest::InitializeRequest(initialize) => {
+                Some(&initialize.params.protocol_version)
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Create a session speaking `protocol_version` and hand its transport to the service loop.
+async fn create_session(
+    app: &App,
+    protocol_version: ProtocolVersion,
+) -> Result<(SessionId, Arc<Session>), Response> {
+    let id = session_id();
+    let (to_service, from_client) = mpsc::channel(CHANNEL_CAPACITY);
+    let (to_client, mut from_service) = mpsc::channel(CHANNEL_CAPACITY);
+    let ct = app.ct.child_token();
+    let session = Arc::new(Session {
+        to_service,
+        streams: Default::default(),
+        ct: ct.clone(),
+    });
+
+    let transport =
+        ChannelTransport::new(to_client, from_client, ct).protocol_version(protocol_version);
+    if app.transport_tx.send(transport).is_err() {
+        warn!("send transport out error");
+        return Err(error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "fail to send out transport, it seems server is closed",
+        ));
+    }
+    app.sessions
+        .write()
+        .await
+        .insert(id.clone(), session.clone());
+    info!(session = %id, "streamable http session created");
+
+    // Route everything the service sends until it shuts down
+    let sessions = app.sessions.clone();
+    let streams = session.streams.clone();
+    let session_id = id.clone();
+    tokio::spawn(async move {
+        while let Some(message) = from_service.recv().await {
+            let mut streams = streams.lock().expect("streams lock poisoned");
+            if let Err(e) = streams.dispatch(&message) {
+                error!(session = %session_id, "failed to serialize message: {}", e);
+            }
+        }
+        sessions.write().await.remove(&session_id);
+        info!(session = %session_id, "streamable http session closed");
+    });
+
+    Ok((id, session))
+}
+
+async fn post_handler(State(app): State<App>, headers: HeaderMap, body: Bytes) -> Response {
+    let messages = match parse_messages(&body) {
+        Ok(messages) if !messages.is_empty() => messages,
+        Ok(_) => return error_response(StatusCode::BAD_REQUEST, "empty batch"),
+        Err(e) => {
+            return error_response(StatusCode::BAD_REQUEST, &format!("invalid message: {e}"));
+        }
+    };
+
+    let (session_id, session) = if let Some(requested) = messages.iter().find_map(requested_version)
+    {
+        if header_session(&headers).is_some() {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                "initialize must not carry an Mcp-Session-Id",
+            );
+        }
+        match create_session(&app, negotiate(requested)).await {
+            Ok((id, session)) => (Some(id), session),
+            Err(response) => return response,
+        }
+    } else {
+        match find_session(&app, &headers).await {
+            Ok(session) => (None, session),
+            Err(response) => return response,
+        }
+    };
+
+    let request_ids: Vec<RequestId> = messages
+        .iter()
+        .filter_map(|message| match message {
+            JsonRpcMessage::Request(request) => Some(request.id.clone()),
+            _ => None,
+        })
+        .collect();
+    // Register the response stream before the service can possibly answer
+    let rx = {
+        let mut streams = session.lock();
+        streams.touch();
+        (!request_ids.is_empty()).then(|| streams.open_post_stream(request_ids))
+    };
+
+    for message in messages {
+        debug!(?message, "new client message");
+        if session.to_service.send(message).await.is_err() {
+            error!("send message error");
+            return error_response(StatusCode::GONE, "session closed");
+        }
+    }
+
+    let mut response = match rx {
+        Some(rx) => sse_response(rx).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    };
+    if let Some(id) = session_id
+        && let Ok(value) = HeaderValue::from_str(&id)
+    {
+        response.headers_mut().insert(SESSION_ID_HEADER, value);
+    }
+    response
+}
+
+async fn get_handler(State(app): State<App>, headers: HeaderMap) -> Response {
+    let session = match find_session(&app, &headers).await {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let last_event_id = match headers.get(LAST_EVENT_ID_HEADER) {
+        Some(value) => match value.to_str().ok().and_then(|v| v.parse().ok()) {
+            Some(id) => Some(id),
+            None => return error_response(StatusCode::BAD_REQUEST, "invalid Last-Event-ID"),
+        },
+        None => None,
+    };
+    debug!(?last_event_id, "streamable http stream opened");
+    let rx = {
+        let mut streams = session.lock();
+        streams.touch();
+        streams.resume(last_event_id)
+    };
+    sse_response(rx).into_response()
+}
+
+async fn delete_handler(State(app): State<App>, headers: HeaderMap) -> Response {
+    let Some(id) = header_session(&headers) else {
+        return error_response(StatusCode::BAD_REQUEST, "missing Mcp-Session-Id header");
+    };
+    match app.sessions.write().await.remove(id) {
+        Some(session) => {
+            session.end();
+            info!(session = %id, "streamable http session terminated by client");
+            StatusCode::OK.into_response()
+        }
+        None => error_response(StatusCode::NOT_FOUND, "unknown session"),
+    }
+}
+
+/// Every so often, end the sessions that have been idle for `timeout`.
+async fn expire_idle(sessions: Sessions, timeout: Duration, ct: CancellationToken) {
+    let mut sweep = tokio::time::interval((timeout / 2).max(Duration::from_secs(1)));
+    loop {
+        tokio::select! {
+            _ = ct.cancelled() => return,
+            _ = sweep.tick() => {}
+        }
+        let mut sessions = sessions.write().await;
+        sessions.retain(|id, session| {
+            if !session.lock().idle(timeout) {
+                return true;
+            }
+            info!(session = %id, "streamable http session expired after {}s idle", timeout.as_secs());
+            session.end();
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version(version: &str) -> ProtocolVersion {
+        serde_json::from_value(version.into()).unwrap()
+    }
+
+    #[test]
+    fn negotiates_the_clients_version_when_supported() {
+        assert_eq!(negotiate(&version("2025-03-26")), version("2025-03-26"));
+        assert_eq!(
+            negotiate(&ProtocolVersion::V_2024_11_05),
+            ProtocolVersion::V_2024_11_05
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_newest_supported_version() {
+        assert_eq!(negotiate(&version("2099-01-01")), version("2025-03-26"));
+        assert_eq!(negotiate(&version("2024-01-01")), version("2025-03-26"));
+    }
+}
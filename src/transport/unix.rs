@@ -0,0 +1,220 @@
+//! Unix domain socket transport.
+//!
+//! Every connection is its own MCP session speaking newline-delimited
+//! JSON-RPC, exactly like stdio, so local tools can connect without a TCP
+//! port being opened. Access is controlled by the socket file's permissions.
+
+use std::fs::{DirBuilder, Permissions};
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use rmcp::{RoleServer, Service};
+use tokio::io;
+use tokio::net::{UnixListener, UnixStream};
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{Instrument, debug, error, info, info_span, warn};
+
+/// Pause after a failed `accept`, which usually means we're out of file
+/// descriptors and retrying at once would just spin.
+const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone)]
+pub struct UnixSocketConfig {
+    pub path: PathBuf,
+    /// Permission bits applied to the socket file, e.g. `0o600`
+    pub mode: u32,
+    /// Ends every session served by this listener
+    pub ct: CancellationToken,
+    /// Stops accepting connections while existing sessions keep running
+    pub listener_ct: CancellationToken,
+    /// Runs the listener and its sessions so shutdown can wait for them to finish
+    pub tasks: TaskTracker,
+}
+
+#[derive(Debug)]
+pub struct UnixSocketServer {
+    listener: UnixListener,
+    socket: SocketFile,
+    pub config: UnixSocketConfig,
+}
+
+impl UnixSocketServer {
+    pub async fn serve_with_config(config: UnixSocketConfig) -> io::Result<Self> {
+        remove_stale_socket(&config.path).await?;
+        let listener = bind_private(&config.path, config.mode)?;
+        let socket = SocketFile(config.path.clone());
+        Ok(Self {
+            listener,
+            socket,
+            config,
+        })
+    }
+
+    /// Serve a fresh service from `service_provider` for every connection.
+    pub fn with_service<S, F>(self, service_provider: F) -> CancellationToken
+    where
+        S: Service<RoleServer>,
+        F: Fn() -> S + Send + 'static,
+    {
+        let ct = self.config.ct.clone();
+        let span = info_span!("unix-server", path = %self.config.path.display());
+        let tasks = self.config.tasks.clone();
+        tasks.spawn(
+            async move {
+                // Owned by the accept loop so the socket file goes away with it
+                let _socket = self.socket;
+                loop {
+                    let stream = tokio::select! {
+                        _ = self.config.listener_ct.cancelled() => {
+                            info!("server cancelled");
+                            break;
+                        }
+                        accepted = self.listener.accept() => match accepted {
+                            Ok((stream, _)) => stream,
+                            Err(e) => {
+                                error!(error = %e, "failed to accept connection");
+                                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                                continue;
+                            }
+                        },
+                    };
+                    let pid = stream.peer_cred().ok().and_then(|cred| cred.pid());
+                    info!(?pid, "unix socket connection");
+                    let service = service_provider();
+                    let ct = self.config.ct.child_token();
+                    self.config.tasks.spawn(
+                        async move {
+                            let result = super::run_session(service, stream.into_split(), ct).await;
+                            match &result {
+                                Ok(()) => debug!("unix socket session ended"),
+                                Err(e) => warn!(error = %e, "unix socket session failed"),
+                            }
+                            result
+                        }
+                        .in_current_span(),
+                    );
+                }
+            }
+            .instrument(span),
+        );
+        ct
+    }
+}
+
+/// Removes the socket file when the server stops.
+#[derive(Debug)]
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_file(&self.0)
+            && e.kind() != io::ErrorKind::NotFound
+        {
+            warn!(path = %self.0.display(), error = %e, "failed to remove unix socket");
+        }
+    }
+}
+
+/// Bind a socket at `path` that is never connectable with looser permissions than `mode`.
+///
+/// The socket is bound inside a fresh directory only we can enter, given its
+/// mode there, and then hard-linked into place, so there is no moment when it
+/// exists at `path` with the permissions the umask would have given it.
+/// Unlike a rename, the link fails if something appeared at `path` since the
+/// stale socket check, rather than replacing another process's socket.
+fn bind_private(path: &Path, mode: u32) -> io::Result<UnixListener> {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is not a file path", path.display()),
+        )
+    })?;
+    let staging = parent.join(format!(
+        ".{}.{:08x}",
+        name.to_string_lossy(),
+        rand::random::<u32>()
+    ));
+    DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join(name);
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, Permissions::from_mode(mode))?;
+        std::fs::hard_link(&staged, path).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("{} was created by another process", path.display()),
+            ),
+            _ => e,
+        })?;
+        Ok(listener)
+    });
+    // Only drops the staged name; the socket stays reachable at `path` once linked
+    if let Err(e) = std::fs::remove_dir_all(&staging) {
+        warn!(path = %staging.display(), error = %e, "failed to remove staging directory");
+    }
+    bound
+}
+
+/// Clear a socket left behind by a server that didn't shut down cleanly.
+///
+/// Refuses to touch anything that isn't a socket, or a socket another
+/// process is still accepting connections on.
+async fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    let metadata = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    if !metadata.file_type().is_socket() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} exists and is not a socket", path.display()),
+        ));
+    }
+    match UnixStream::connect(path).await {
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AddrInUse,
+            format!("{} is in use by another server", path.display()),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => {
+            info!(path = %path.display(), "removing stale unix socket");
+            std::fs::remove_file(path)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+
+    #[tokio::test]
+    async fn binds_with_the_requested_mode() {
+        let dir = TempDir::new("unix-bind");
+        let path = dir.join("mcp.sock");
+        let _listener = bind_private(&path, 0o600).unwrap();
+        let metadata = std::fs::symlink_metadata(&path).unwrap();
+        assert!(metadata.file_type().is_socket());
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        UnixStream::connect(&path).await.unwrap();
+        // Only the socket is left behind, not the staging directory
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn never_replaces_an_existing_path() {
+        let dir = TempDir::new("unix-bind-existing");
+        let path = dir.join("mcp.sock");
+        let _other = bind_private(&path, 0o600).unwrap();
+        let error = bind_private(&path, 0o600).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        UnixStream::connect(&path).await.unwrap();
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
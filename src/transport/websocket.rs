@@ -0,0 +1,213 @@
+//! WebSocket transport.
+//!
+//! Clients upgrade a `GET` on the endpoint and then exchange one JSON-RPC
+//! message per text message in both directions, so browsers don't need the
+//! SSE stream plus `POST` back-channel. The server pings idle connections and
+//! drops those that stop answering.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::{
+    Router,
+    extract::{
+        State,
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade, close_code},
+    },
+    response::Response,
+    routing::get,
+};
+use rmcp::{RoleServer, Service, model::ClientJsonRpcMessage};
+use tokio::io;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+use tracing::{debug, info, warn};
+
+use super::ChannelTransport;
+use super::auth::TokenAuth;
+use super::tls::TlsConfig;
+use crate::status::ServerStatus;
+
+const CHANNEL_CAPACITY: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct WebSocketConfig {
+    pub bind: SocketAddr,
+    pub path: String,
+    /// Ends every session served by this listener
+    pub ct: CancellationToken,
+    /// Stops accepting connections while existing sessions keep running
+    pub listener_ct: CancellationToken,
+    /// Runs the listener and its sessions so shutdown can wait for them to finish
+    pub tasks: TaskTracker,
+    /// Require a bearer token on every request when set
+    pub auth: Option<TokenAuth>,
+    /// Serve HTTPS with this certificate when set
+    pub tls: Option<TlsConfig>,
+    /// Backs the `/healthz`, `/readyz`, `/info` and `/metrics` routes
+    pub status: ServerStatus,
+    /// Largest message, and frame, accepted from a client in bytes
+    pub max_message_size: usize,
+    /// How often to ping; a connection that hasn't answered by the next ping is closed
+    pub ping_interval: Duration,
+}
+
+#[derive(Debug)]
+pub struct WebSocketServer {
+    transport_rx: mpsc::UnboundedReceiver<ChannelTransport>,
+    pub config: WebSocketConfig,
+}
+
+impl WebSocketServer {
+    pub async fn serve_with_config(config: WebSocketConfig) -> io::Result<Self> {
+        let (transport_tx, transport_rx) = mpsc::unbounded_channel();
+        let app = App {
+            transport_tx,
+            max_message_size: config.max_message_size,
+            ping_interval: config.ping_interval,
+            ct: config.ct.clone(),
+            tasks: config.tasks.clone(),
+        };
+        let router = super::with_status_routes(
+            Router::new()
+                .route(&config.path, get(upgrade_handler))
+                .with_state(app),
+            &config.status,
+            config.auth.as_ref(),
+        );
+        super::serve_router(
+            config.bind,
+            router,
+            config.listener_ct.clone(),
+            tracing::info_span!("websocket-server", bind_address = %config.bind),
+            config.tls.clone(),
+            &config.tasks,
+        )
+        .await?;
+        Ok(Self {
+            transport_rx,
+            config,
+        })
+    }
+
+    /// Serve a fresh service from `service_provider` for every connection.
+    pub fn with_service<S, F>(self, service_provider: F) -> CancellationToken
+    where
+        S: Service<RoleServer>,
+        F: Fn() -> S + Send + 'static,
+    {
+        super::spawn_sessions(self.transport_rx, service_provider, self.config.tasks);
+        self.config.ct
+    }
+}
+
+#[derive(Clone)]
+struct App {
+    transport_tx: mpsc::UnboundedSender<ChannelTransport>,
+    max_message_size: usize,
+    ping_interval: Duration,
+    ct: CancellationToken,
+    tasks: TaskTracker,
+}
+
+async fn upgrade_handler(State(app): State<App>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade
+        .max_message_size(app.max_message_size)
+        .max_frame_size(app.max_message_size)
+        .on_failed_upgrade(|e| warn!(error = %e, "websocket upgrade failed"))
+        .on_upgrade(move |socket| run_connection(app, socket))
+}
+
+/// Pump messages between one socket and its service until either side goes away.
+async fn run_connection(app: App, mut socket: WebSocket) {
+    // Upgraded connections outlive the HTTP server, so track them separately
+    let _task = app.tasks.token();
+    info!("websocket connection");
+    let (to_service, from_client) = mpsc::channel(CHANNEL_CAPACITY);
+    let (to_client, mut from_service) = mpsc::channel(CHANNEL_CAPACITY);
+    let transport = ChannelTransport::new(to_client, from_client, app.ct.child_token());
+    if app.transport_tx.send(transport).is_err() {
+        warn!("send transport out error");
+        let _ = socket
+            .send(close(close_code::AWAY, "server is shutting down"))
+            .await;
+        return;
+    }
+
+    let mut ping = tokio::time::interval(app.ping_interval);
+    ping.tick().await;
+    let mut answered = true;
+    let reason = loop {
+        tokio::select! {
+            _ = app.ct.cancelled() => {
+                break Some(close(close_code::AWAY, "server is shutting down"));
+            }
+            _ = ping.tick() => {
+                if !answered {
+                    warn!("websocket client stopped answering pings");
+                    break None;
+                }
+                answered = false;
+                if socket.send(Message::Ping(Default::default())).await.is_err() {
+                    break None;
+                }
+            }
+            message = from_service.recv() => {
+                let Some(message) = message else {
+                    break Some(close(close_code::NORMAL, "session ended"));
+                };
+                let text = match serde_json::to_string(&message) {
+                    Ok(text) => text,
+                    Err(e) => {
+                        warn!(error = %e, "failed to encode server message");
+                        continue;
+                    }
+                };
+                if socket.send(Message::text(text)).await.is_err() {
+                    break None;
+                }
+            }
+            message = socket.recv() => {
+                // Any traffic shows the client is alive, not just pongs
+                answered = true;
+                match message {
+                    Some(Ok(Message::Text(text))) => {
+                        match serde_json::from_str::<ClientJsonRpcMessage>(&text) {
+                            Ok(message) => {
+                                debug!(?message, "new client message");
+                                if to_service.send(message).await.is_err() {
+                                    break Some(close(close_code::NORMAL, "session ended"));
+                                }
+                            }
+                            Err(e) => {
+                                warn!(error = %e, "invalid JSON-RPC message");
+                                break Some(close(close_code::INVALID, "invalid JSON-RPC message"));
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Binary(_))) => {
+                        break Some(close(close_code::UNSUPPORTED, "send JSON-RPC as text messages"));
+                    }
+                    Some(Ok(Message::Ping(_) | Message::Pong(_))) => {}
+                    Some(Ok(Message::Close(_))) | None => break None,
+                    Some(Err(e)) => {
+                        warn!(error = %e, "websocket error");
+                        break None;
+                    }
+                }
+            }
+        }
+    };
+    if let Some(reason) = reason {
+        let _ = socket.send(reason).await;
+    }
+    info!("websocket connection closed");
+}
+
+fn close(code: u16, reason: &'static str) -> Message {
+    Message::Close(Some(CloseFrame {
+        code,
+        reason: reason.into(),
+    }))
+}